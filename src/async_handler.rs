@@ -0,0 +1,596 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::platform;
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+use crate::{init_and_register, Error, HandlerConfig, ShutdownHandle, Signal};
+#[cfg(feature = "tokio")]
+use crate::{init_and_set_handler, Error, Signal};
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+use async_std::stream::StreamExt;
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+use smol::stream::StreamExt;
+use std::future::Future;
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
+
+/// Register signal handler in tokio runtime for Ctrl-C.
+///
+/// The signals are received with `tokio::signal`, and no handler is registered with this crate.
+/// Aborting the returned task, or dropping the runtime, drops the `tokio::signal` listeners, so
+/// `set_async_handler()` can be called again afterwards. Like with any use of `tokio::signal`,
+/// the signals stay trapped for the rest of the process.
+#[cfg(feature = "tokio")]
+pub async fn set_async_handler<F>(user_handler: F) -> tokio::task::JoinHandle<()>
+where
+    F: Future<Output = ()> + 'static + Send,
+{
+    tokio::spawn(async move {
+        let block = async move {
+            TokioSignals::new()?.recv().await;
+
+            user_handler.await;
+
+            Ok::<(), std::io::Error>(())
+        };
+        if let Err(err) = block.await {
+            eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
+        }
+    })
+}
+
+/// Register signal handler in tokio runtime for Ctrl-C whose future is not `Send`.
+///
+/// Works like [`set_async_handler()`](fn.set_async_handler.html), but the task is spawned with
+/// [`tokio::task::spawn_local()`], so `user_handler` can hold `!Send` state like an `Rc`, e.g. in
+/// a `current_thread` runtime. This must run within a [`LocalSet`](tokio::task::LocalSet),
+/// otherwise `spawn_local()` panics.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let state = std::rc::Rc::new(std::cell::Cell::new(0));
+/// let local = tokio::task::LocalSet::new();
+/// local
+///     .run_until(async move {
+///         let handle = ctrlc2::set_async_handler_local(async move {
+///             state.set(state.get() + 1);
+///             println!("Got Ctrl-C");
+///         })
+///         .await;
+///         handle.await.unwrap();
+///     })
+///     .await;
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn set_async_handler_local<F>(user_handler: F) -> tokio::task::JoinHandle<()>
+where
+    F: Future<Output = ()> + 'static,
+{
+    tokio::task::spawn_local(async move {
+        let block = async move {
+            TokioSignals::new()?.recv().await;
+
+            user_handler.await;
+
+            Ok::<(), std::io::Error>(())
+        };
+        if let Err(err) = block.await {
+            eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
+        }
+    })
+}
+
+/// Register signal handler in tokio runtime for Ctrl-C that gives up after `timeout`.
+///
+/// Works like [`set_async_handler()`](fn.set_async_handler.html), but if no signal arrives
+/// within `timeout`, the task ends without running `user_handler`. The task resolves to whether
+/// a signal was received, which makes the async handler usable in tests and CI, where nobody
+/// presses Ctrl-C.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let received = ctrlc2::set_async_handler_with_timeout(
+///     std::time::Duration::from_secs(5),
+///     async { println!("Got Ctrl-C") },
+/// )
+/// .await
+/// .await
+/// .unwrap();
+/// if !received {
+///     println!("No Ctrl-C within five seconds");
+/// }
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn set_async_handler_with_timeout<F>(
+    timeout: std::time::Duration,
+    user_handler: F,
+) -> tokio::task::JoinHandle<bool>
+where
+    F: Future<Output = ()> + 'static + Send,
+{
+    tokio::spawn(async move {
+        let block = async move {
+            let mut signals = TokioSignals::new()?;
+            if tokio::time::timeout(timeout, signals.recv()).await.is_err() {
+                return Ok(false);
+            }
+
+            user_handler.await;
+
+            Ok::<bool, std::io::Error>(true)
+        };
+        match block.await {
+            Ok(received) => received,
+            Err(err) => {
+                eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
+                false
+            }
+        }
+    })
+}
+
+/// Register signal handler in tokio runtime that runs on every Ctrl-C.
+///
+/// Unlike [`set_async_handler()`](fn.set_async_handler.html), which runs a single future once,
+/// `user_handler` is a factory that is called for each signal. The task keeps handling signals
+/// until a future returned by the factory resolves to `true`, like the handler of
+/// [`set_handler()`](fn.set_handler.html).
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let mut count = 0;
+/// ctrlc2::set_async_handler_repeating(move || {
+///     count += 1;
+///     async move {
+///         println!("Received Ctrl-C {} times", count);
+///         count == 3
+///     }
+/// })
+/// .await;
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn set_async_handler_repeating<F, Fut>(mut user_handler: F) -> tokio::task::JoinHandle<()>
+where
+    F: FnMut() -> Fut + 'static + Send,
+    Fut: Future<Output = bool> + 'static + Send,
+{
+    tokio::spawn(async move {
+        let block = async move {
+            let mut signals = TokioSignals::new()?;
+            loop {
+                signals.recv().await;
+                if user_handler().await {
+                    break;
+                }
+            }
+
+            Ok::<(), std::io::Error>(())
+        };
+        if let Err(err) = block.await {
+            eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
+        }
+    })
+}
+
+/// Register a signal handler for Ctrl-C that yields each received signal from a tokio stream.
+///
+/// The signal handling thread sends every signal into a `tokio::sync::mpsc` channel, whose
+/// receiver is returned as a [`Stream`](tokio_stream::Stream), so repeated signals can be
+/// consumed with `tokio_stream::StreamExt`, unlike with
+/// [`set_async_handler()`](fn.set_async_handler.html). The stream ends when the handler is unset
+/// with [`unset_handler()`](fn.unset_handler.html), and the signal handling thread stops on the
+/// next signal after the stream has been dropped.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// use tokio_stream::StreamExt;
+///
+/// let mut signals = ctrlc2::signal_stream_tokio().expect("Error setting Ctrl-C handler");
+/// while let Some(signal) = signals.next().await {
+///     println!("Got {:?}", signal);
+/// }
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+#[cfg(feature = "tokio")]
+pub fn signal_stream_tokio() -> Result<impl tokio_stream::Stream<Item = Signal>, Error> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |signal| tx.send(signal).is_err(),
+        Default::default(),
+    )?;
+    Ok(tokio_stream::wrappers::UnboundedReceiverStream::new(rx))
+}
+
+/// Register a signal handler for Ctrl-C that wakes all tasks waiting on a tokio
+/// [`Notify`](tokio::sync::Notify).
+///
+/// The signal handling thread calls [`notify_waiters()`](tokio::sync::Notify::notify_waiters)
+/// on each signal, so any number of tasks can coordinate shutdown by awaiting
+/// [`notified()`](tokio::sync::Notify::notified) on the returned `Notify`. Unlike
+/// [`set_async_handler()`](fn.set_async_handler.html), the handler is registered with this
+/// crate, stays set until unset with [`unset_handler()`](fn.unset_handler.html), and doesn't
+/// need to be called from within a runtime.
+///
+/// `notify_waiters()` only wakes tasks that are already waiting: a signal received before a task
+/// has created or [enabled](tokio::sync::futures::Notified::enable) its `Notified` future is
+/// not seen by that task.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let (notify, _) = ctrlc2::set_notify().expect("Error setting Ctrl-C handler");
+/// for i in 0..4 {
+///     let notify = notify.clone();
+///     tokio::spawn(async move {
+///         notify.notified().await;
+///         println!("Task {} shutting down", i);
+///     });
+/// }
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+#[cfg(feature = "tokio")]
+pub fn set_notify() -> Result<(Arc<tokio::sync::Notify>, std::thread::JoinHandle<()>), Error> {
+    let notify = Arc::new(tokio::sync::Notify::new());
+    let waiters = Arc::clone(&notify);
+    let handle = init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            waiters.notify_waiters();
+            false
+        },
+        Default::default(),
+    )?;
+    Ok((notify, handle))
+}
+
+/// Register a signal handler for Ctrl-C that cancels a
+/// [`CancellationToken`](tokio_util::sync::CancellationToken).
+///
+/// The token is cancelled on the first signal, which also ends the signal handling thread. Hand
+/// clones or [child tokens](tokio_util::sync::CancellationToken::child_token) of it to the
+/// tasks that take part in a graceful shutdown, and let them observe
+/// [`cancelled()`](tokio_util::sync::CancellationToken::cancelled). Unlike with
+/// [`set_notify()`](fn.set_notify.html), tasks that start waiting after the signal see the
+/// cancellation as well.
+///
+/// Only available with the `tokio-util` feature.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let (token, _) = ctrlc2::set_cancellation_token().expect("Error setting Ctrl-C handler");
+/// let worker = tokio::spawn(async move {
+///     token.cancelled().await;
+///     println!("Shutting down");
+/// });
+/// worker.await.unwrap();
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+#[cfg(feature = "tokio-util")]
+pub fn set_cancellation_token() -> Result<
+    (
+        tokio_util::sync::CancellationToken,
+        std::thread::JoinHandle<()>,
+    ),
+    Error,
+> {
+    let token = tokio_util::sync::CancellationToken::new();
+    let cancel = token.clone();
+    let handle = init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            cancel.cancel();
+            true
+        },
+        Default::default(),
+    )?;
+    Ok((token, handle))
+}
+
+/// Register signal handler for Ctrl-C without blocking the tokio runtime.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but the registration, which waits for
+/// other threads setting or unsetting a handler and spawns the signal handling thread, is run
+/// with [`tokio::task::spawn_blocking()`]. The returned future resolves once the handler is
+/// set. `user_handler` still runs on the signal handling thread, not in the runtime.
+///
+/// Only available with the `tokio` feature.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// ctrlc2::arm_handler(|| {
+///     println!("Got Ctrl-C! Exiting...");
+///     true
+/// })
+/// .await
+/// .expect("Error setting Ctrl-C handler");
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, an error if a system
+/// error occurred while setting the handler, or an error of kind
+/// [`Other`](std::io::ErrorKind::Other) if the runtime shut down before the handler was set.
+#[cfg(feature = "tokio")]
+pub async fn arm_handler<F>(user_handler: F) -> Result<std::thread::JoinHandle<()>, Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    tokio::task::spawn_blocking(move || crate::set_handler(user_handler))
+        .await
+        .map_err(|e| Error::System(std::io::Error::new(std::io::ErrorKind::Other, e)))?
+}
+
+/// Wait for Ctrl-C in the current tokio task and return the received signal.
+///
+/// Unlike [`set_async_handler()`](fn.set_async_handler.html), no task is spawned, so the caller
+/// decides where to await the signal, e.g. in a `tokio::select!` against other futures. The
+/// signals are received with `tokio::signal` like with `set_async_handler()`, and no handler is
+/// registered with this crate. This is the async version of
+/// [`wait_for_signal()`](fn.wait_for_signal.html).
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// tokio::select! {
+///     signal = ctrlc2::wait_for_signal_async() => {
+///         println!("Got {:?}! Exiting...", signal.expect("Error waiting for Ctrl-C"));
+///     }
+///     _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => println!("Done"),
+/// }
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting up the `tokio::signal`
+/// listeners.
+#[cfg(feature = "tokio")]
+pub async fn wait_for_signal_async() -> Result<Signal, Error> {
+    let mut signals = TokioSignals::new().map_err(Error::System)?;
+    Ok(signals.recv().await)
+}
+
+/// Tokio listeners for the signals handled by [`set_handler()`](fn.set_handler.html).
+///
+/// The listeners are kept across signals, so no signal is missed between two calls of
+/// [`TokioSignals::recv()`].
+#[cfg(feature = "tokio")]
+struct TokioSignals {
+    #[cfg(unix)]
+    signals: Vec<tokio::signal::unix::Signal>,
+    #[cfg(windows)]
+    ctrl_c: tokio::signal::windows::CtrlC,
+}
+
+#[cfg(feature = "tokio")]
+impl TokioSignals {
+    fn new() -> std::io::Result<Self> {
+        #[cfg(unix)]
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(TokioSignals {
+            #[cfg(unix)]
+            signals: platform::DEFAULT_SIGNALS
+                .iter()
+                .map(|&sig| signal(SignalKind::from_raw(sig as std::os::raw::c_int)))
+                .collect::<std::io::Result<_>>()?,
+            #[cfg(windows)]
+            ctrl_c: tokio::signal::windows::ctrl_c()?,
+        })
+    }
+
+    /// Wait for the next signal and return it.
+    async fn recv(&mut self) -> Signal {
+        #[cfg(unix)]
+        return std::future::poll_fn(|cx| {
+            for (signal, &sig) in self.signals.iter_mut().zip(platform::DEFAULT_SIGNALS) {
+                if signal.poll_recv(cx).is_ready() {
+                    return std::task::Poll::Ready(sig);
+                }
+            }
+            std::task::Poll::Pending
+        })
+        .await;
+
+        #[cfg(windows)]
+        {
+            self.ctrl_c.recv().await;
+            windows_sys::Win32::System::Console::CTRL_C_EVENT
+        }
+    }
+}
+
+/// Register signal handler in async-std runtime for Ctrl-C.
+///
+/// The handler is registered like [`set_handler()`](fn.set_handler.html) would, and the signal
+/// is passed from the signal handling thread to the spawned task. Like the tokio version,
+/// `user_handler` runs once, after the first signal. The `termination` and `quit` features are
+/// honored.
+///
+/// The handler is unset again once `user_handler` has completed, or when the returned task is
+/// cancelled before that, see [`unset_handler()`](fn.unset_handler.html). So unlike with the
+/// tokio version, the signals are no longer trapped afterwards, and `set_async_handler()` can
+/// be called again.
+///
+/// If the `tokio` feature is enabled as well, the tokio version is used instead.
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub async fn set_async_handler<F>(user_handler: F) -> async_std::task::JoinHandle<()>
+where
+    F: Future<Output = ()> + 'static + Send,
+{
+    async_std::task::spawn(handle_once(user_handler))
+}
+
+/// Register signal handler in async-std runtime that runs on every Ctrl-C.
+///
+/// See the tokio version of
+/// [`set_async_handler_repeating()`](fn.set_async_handler_repeating.html); the signals are
+/// passed from the signal handling thread to the spawned task. The handler is unset once
+/// `user_handler` returned `true` or the returned task is cancelled.
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub async fn set_async_handler_repeating<F, Fut>(user_handler: F) -> async_std::task::JoinHandle<()>
+where
+    F: FnMut() -> Fut + 'static + Send,
+    Fut: Future<Output = bool> + 'static + Send,
+{
+    async_std::task::spawn(handle_repeating(user_handler))
+}
+
+/// Register signal handler in smol runtime for Ctrl-C.
+///
+/// Works like the async-std version: the signal is passed from the signal handling thread to a
+/// task spawned on the global smol executor, and `user_handler` runs once, after the first
+/// signal. The `termination` and `quit` features are honored.
+///
+/// Dropping a [`smol::Task`] cancels it, so the returned task has to be awaited or
+/// [detached](smol::Task::detach). The handler is unset again once `user_handler` has
+/// completed, or when the task is cancelled before that.
+///
+/// If the `tokio` or `async-std` feature is enabled as well, that version is used instead.
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+pub async fn set_async_handler<F>(user_handler: F) -> smol::Task<()>
+where
+    F: Future<Output = ()> + 'static + Send,
+{
+    smol::spawn(handle_once(user_handler))
+}
+
+/// Register signal handler in smol runtime that runs on every Ctrl-C.
+///
+/// See the tokio version of
+/// [`set_async_handler_repeating()`](fn.set_async_handler_repeating.html); the signals are
+/// passed from the signal handling thread to the spawned task. The handler is unset once
+/// `user_handler` returned `true`, or when the returned [`smol::Task`] is dropped, which
+/// cancels it.
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+pub async fn set_async_handler_repeating<F, Fut>(user_handler: F) -> smol::Task<()>
+where
+    F: FnMut() -> Fut + 'static + Send,
+    Fut: Future<Output = bool> + 'static + Send,
+{
+    smol::spawn(handle_repeating(user_handler))
+}
+
+/// Wait for Ctrl-C in the current task and return the received signal.
+///
+/// See the tokio version of [`wait_for_signal_async()`](fn.wait_for_signal_async.html). The
+/// handler is registered like [`set_handler()`](fn.set_handler.html) would, and unset again once
+/// the signal was received or the returned future is dropped.
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler. If the handler is unset with
+/// [`unset_handler()`](fn.unset_handler.html) in the meantime, an [`Error::System`] of kind
+/// [`Interrupted`](std::io::ErrorKind::Interrupted) is returned.
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+pub async fn wait_for_signal_async() -> Result<Signal, Error> {
+    let mut signals = bridge()?;
+    signals
+        .rx
+        .next()
+        .await
+        .ok_or_else(|| Error::System(std::io::ErrorKind::Interrupted.into()))
+}
+
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+async fn handle_once<F>(user_handler: F)
+where
+    F: Future<Output = ()>,
+{
+    let mut signals = match bridge() {
+        Ok(signals) => signals,
+        Err(err) => {
+            eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
+            return;
+        }
+    };
+
+    if signals.rx.next().await.is_some() {
+        user_handler.await;
+    }
+}
+
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+async fn handle_repeating<F, Fut>(mut user_handler: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut signals = match bridge() {
+        Ok(signals) => signals,
+        Err(err) => {
+            eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
+            return;
+        }
+    };
+
+    while signals.rx.next().await.is_some() {
+        if user_handler().await {
+            break;
+        }
+    }
+}
+
+/// Signals passed from the signal handling thread to async code, see [`bridge()`].
+///
+/// The handler is unset when this is dropped, i.e. once the task using it completed or was
+/// cancelled.
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+struct Bridge {
+    rx: futures_channel::mpsc::UnboundedReceiver<Signal>,
+    shutdown: ShutdownHandle,
+}
+
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown.shutdown() {
+            eprintln!("Error unsetting Ctrl-C handler: {}", e);
+        }
+    }
+}
+
+/// Set a handler that passes the signals from the signal handling thread to async code.
+#[cfg(all(any(feature = "async-std", feature = "smol"), not(feature = "tokio")))]
+fn bridge() -> Result<Bridge, Error> {
+    let (tx, rx) = futures_channel::mpsc::unbounded();
+    let (_, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |signal| tx.unbounded_send(signal).is_err(),
+        Default::default(),
+        &HandlerConfig::default(),
+    )?;
+    Ok(Bridge {
+        rx,
+        shutdown: ShutdownHandle {
+            source: std::sync::Arc::downgrade(&source),
+        },
+    })
+}
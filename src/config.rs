@@ -0,0 +1,371 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{init_and_register, platform, Error, Signal};
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Callback invoked with the payload of a panic in the user handler, deciding how to go on, see
+/// [`HandlerConfig::on_panic`].
+pub type PanicCallback = Arc<dyn Fn(Box<dyn Any + Send>) -> PanicPolicy + Send + Sync>;
+
+/// How the signal handling thread goes on after a panic in the user handler, as returned by
+/// the [`HandlerConfig::on_panic`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Keep handling signals, the default.
+    Continue,
+    /// Stop handling signals and exit the signal handling thread. The handler stays set, so
+    /// unset it before setting a new one.
+    Stop,
+    /// Exit the process with the given exit code, with [`std::process::exit()`].
+    Exit(i32),
+}
+
+/// Keep handling signals, for [`Builder::on_panic`] callbacks that return nothing.
+impl From<()> for PanicPolicy {
+    fn from(_: ()) -> Self {
+        PanicPolicy::Continue
+    }
+}
+
+/// Callback invoked with a fatal error while waiting for signals, see
+/// [`HandlerConfig::on_error`].
+pub type ErrorCallback = Arc<dyn Fn(Error) + Send + Sync>;
+
+/// Configuration of the signal handling thread, see
+/// [`set_handler_with_config()`](fn.set_handler_with_config.html).
+///
+/// The default configuration is the one used by [`set_handler()`](fn.set_handler.html).
+///
+/// # Example
+/// ```
+/// let config = ctrlc2::HandlerConfig {
+///     name: Some("myapp-ctrl-c".into()),
+///     stack_size: Some(64 * 1024),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Default)]
+pub struct HandlerConfig {
+    /// Name of the signal handling thread. Defaults to `ctrl-c`.
+    pub name: Option<String>,
+    /// Stack size of the signal handling thread in bytes. Defaults to the stack size of
+    /// [`std::thread::spawn()`].
+    pub stack_size: Option<usize>,
+    /// Called on the signal handling thread with the payload of a panic in the user handler,
+    /// and decides whether the thread keeps handling signals, stops, or exits the process, e.g.
+    /// after printing a message. If unset, the panic is reported on stderr and the thread keeps
+    /// handling signals.
+    pub on_panic: Option<PanicCallback>,
+    /// Called on the signal handling thread with a fatal system error while waiting for a
+    /// signal, before the thread exits. If unset, the error is reported on stderr. Transient
+    /// interruptions like `EINTR` are retried and not reported. The handler stays set after the
+    /// thread exited, so unset it before setting a new one.
+    pub on_error: Option<ErrorCallback>,
+    /// Whether system calls interrupted by a handled signal are restarted, i.e. whether the
+    /// Unix signal handlers are installed with `SA_RESTART`. Without it, blocking system calls
+    /// anywhere in the program, like `read(2)` or `accept(2)`, fail with `EINTR` when a signal
+    /// arrives. Defaults to `true`. Ignored on Windows and on QNX Neutrino.
+    pub restart_syscalls: Option<bool>,
+    /// Process to forward each received signal to before the handler runs, as passed to
+    /// `kill(2)`: a child process id, or the negated id of a process group to reach all of its
+    /// members. Children are not forwarded signals by default, they only receive `SIGINT` from
+    /// the terminal if they are in the foreground process group. Ignored on Windows, where
+    /// console control events reach every process attached to the console anyway.
+    pub forward_to: Option<i32>,
+    /// Whether to skip the signals that only a terminal sends, `SIGINT` and `SIGQUIT`, if the
+    /// process has no controlling terminal, e.g. because it was started as a daemon in its own
+    /// session. The other signals, like `SIGTERM` and `SIGHUP` with the `termination` feature,
+    /// are handled regardless, and the handler counts as set even if no signal is left. Use
+    /// [`installed_signals()`](fn.installed_signals.html) to tell which signals are handled.
+    /// Defaults to `false`. Ignored on Windows.
+    pub skip_if_no_tty: bool,
+    /// Window after the handler has run in which further signals are dropped without running
+    /// it again, e.g. while Ctrl-C is held down. The window starts when the handler returns.
+    /// This is debouncing in the signal handling thread, on top of the coalescing of pending
+    /// signals done by the os. Dropped signals are still counted by
+    /// [`signals_received()`](fn.signals_received.html). Returning `true` from the first
+    /// invocation takes effect as usual. Defaults to no debouncing.
+    pub debounce: Option<Duration>,
+    /// Whether to set the disposition of `SIGPIPE` to `SIG_IGN` when the handler is set, so a
+    /// write to a closed pipe or socket fails with `EPIPE` instead of killing the process. The
+    /// Rust runtime already ignores `SIGPIPE` before `main()` in Rust executables, so this
+    /// matters where that is not the case, e.g. in a library loaded by a program written in
+    /// another language, or after `SIGPIPE` was reset to `SIG_DFL`. `SIGPIPE` stays ignored
+    /// after the handler is unset, and child processes created via `execve(2)` inherit this.
+    /// It is left alone if setting the handler fails. Defaults to `false`. Ignored on Windows.
+    pub ignore_sigpipe: bool,
+    /// Whether the handler routine returns `FALSE` for the events it handled, so the routines
+    /// registered before ours see them as well, instead of returning `TRUE` and consuming them.
+    /// Windows calls the handler routines last registered first until one returns `TRUE`, so
+    /// a routine registered after ours that consumes an event keeps ours from seeing it, and
+    /// propagating lets the routines before ours run too. If none of them consumes the event,
+    /// the default routine does, which terminates the process on Ctrl-C. Close, logoff and
+    /// shutdown events are always consumed, as the process is terminated afterwards anyway.
+    /// See also `windows_handler_reachable()`. Defaults to `false`. Ignored on Unix, where
+    /// [`set_handler_chained()`](fn.set_handler_chained.html) invokes the replaced handler.
+    pub propagate_events: bool,
+    /// Whether to register the Unix signal handlers through the registry of the
+    /// [`signal-hook`](https://docs.rs/signal-hook) crate instead of replacing the signal
+    /// dispositions. Handlers registered with `signal-hook`, before or after this one, and
+    /// handlers installed before `signal-hook`'s own keep running alongside ours, so the
+    /// `overwrite` warning of [`set_handler()`](fn.set_handler.html) doesn't apply. For the same
+    /// reason, an existing handler for the signals is never a conflict in this mode: there is
+    /// nothing to overwrite. [`Error::MultipleHandlers`] is still returned if a handler of this
+    /// crate is already set.
+    ///
+    /// The signal handlers are always installed with `SA_RESTART`, so
+    /// [`restart_syscalls`](#structfield.restart_syscalls) is ignored. After the handler is
+    /// unset, `signal-hook`'s signal handler stays installed, like after unregistering with
+    /// `signal-hook` itself, so the signals no longer terminate the process unless something else
+    /// handles them. Defaults to `false`. Ignored on Windows.
+    ///
+    /// Only available with the `signal-hook` feature.
+    #[cfg(feature = "signal-hook")]
+    pub signal_hook: bool,
+}
+
+impl fmt::Debug for HandlerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("HandlerConfig");
+        f.field("name", &self.name)
+            .field("stack_size", &self.stack_size)
+            .field("on_panic", &self.on_panic.as_ref().map(|_| ".."))
+            .field("on_error", &self.on_error.as_ref().map(|_| ".."))
+            .field("restart_syscalls", &self.restart_syscalls)
+            .field("forward_to", &self.forward_to)
+            .field("skip_if_no_tty", &self.skip_if_no_tty)
+            .field("debounce", &self.debounce)
+            .field("ignore_sigpipe", &self.ignore_sigpipe)
+            .field("propagate_events", &self.propagate_events);
+        #[cfg(feature = "signal-hook")]
+        f.field("signal_hook", &self.signal_hook);
+        f.finish()
+    }
+}
+
+impl HandlerConfig {
+    pub(crate) fn report_panic(&self, payload: Box<dyn Any + Send>) -> PanicPolicy {
+        match &self.on_panic {
+            Some(on_panic) => on_panic(payload),
+            None => {
+                eprintln!("Ctrl-C handler panicked, continuing to handle signals");
+                PanicPolicy::Continue
+            }
+        }
+    }
+
+    pub(crate) fn report_error(&self, error: Error) {
+        match &self.on_error {
+            Some(on_error) => on_error(error),
+            None => eprintln!("Critical system error while waiting for Ctrl-C: {}", error),
+        }
+    }
+
+    pub(crate) fn options(&self) -> platform::Options {
+        platform::Options {
+            restart: self.restart_syscalls.unwrap_or(true),
+            chain: self.propagate_events,
+            #[cfg(feature = "signal-hook")]
+            signal_hook: self.signal_hook,
+            ..Default::default()
+        }
+    }
+
+    /// The subset of `signals` to install the os handler for.
+    pub(crate) fn signals(&self, signals: &[Signal]) -> Vec<Signal> {
+        if self.skip_if_no_tty && !platform::has_controlling_terminal() {
+            signals
+                .iter()
+                .filter(|signal| !platform::TERMINAL_SIGNALS.contains(signal))
+                .copied()
+                .collect()
+        } else {
+            signals.to_vec()
+        }
+    }
+
+    /// Apply the dispositions of signals that are not handled, once the handler is set.
+    pub(crate) fn apply_dispositions(&self) -> Result<(), Error> {
+        if self.ignore_sigpipe {
+            platform::ignore_sigpipe()?;
+        }
+        Ok(())
+    }
+
+    /// Whether a signal is dropped because the handler last returned at `last_run`, less than
+    /// the debounce window ago.
+    pub(crate) fn debounced(&self, last_run: Option<Instant>) -> bool {
+        match (self.debounce, last_run) {
+            (Some(window), Some(last_run)) => last_run.elapsed() < window,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn forward(&self, signal: Signal) {
+        if let Some(pid) = self.forward_to {
+            if let Err(e) = platform::forward(pid, signal) {
+                eprintln!("Error forwarding signal to {}: {}", pid, e);
+            }
+        }
+    }
+
+    pub(crate) fn thread_builder(&self) -> thread::Builder {
+        let name = self.name.clone().unwrap_or_else(|| "ctrl-c".into());
+        let builder = thread::Builder::new().name(name);
+        match self.stack_size {
+            Some(size) => builder.stack_size(size),
+            None => builder,
+        }
+    }
+}
+
+/// Builder for a signal handler, as an alternative to the `set_handler*()` functions.
+///
+/// Every setting defaults to what [`set_handler()`](fn.set_handler.html) uses: the signals are
+/// those of [`all_signals()`](fn.all_signals.html), the thread is named `ctrl-c` and gets the
+/// stack size of [`std::thread::spawn()`], interrupted system calls are restarted, and panics
+/// in the handler are reported on stderr.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::Builder::new()
+///     .thread_name("myapp-ctrl-c")
+///     .stack_size(64 * 1024)
+///     .restart_syscalls(false)
+///     .build(|signal| {
+///         println!("Got {:?}", signal);
+///         true
+///     })
+///     .expect("Error setting Ctrl-C handler");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    signals: Option<Vec<Signal>>,
+    config: HandlerConfig,
+}
+
+impl Builder {
+    /// Create a builder with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle `signals` instead of the default ones, like
+    /// [`set_handler_for()`](fn.set_handler_for.html).
+    pub fn signals(mut self, signals: &[Signal]) -> Self {
+        self.signals = Some(signals.to_vec());
+        self
+    }
+
+    /// See [`HandlerConfig::name`].
+    pub fn thread_name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = Some(name.into());
+        self
+    }
+
+    /// See [`HandlerConfig::stack_size`].
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.config.stack_size = Some(size);
+        self
+    }
+
+    /// See [`HandlerConfig::restart_syscalls`].
+    pub fn restart_syscalls(mut self, restart: bool) -> Self {
+        self.config.restart_syscalls = Some(restart);
+        self
+    }
+
+    /// See [`HandlerConfig::on_panic`]. A callback returning `()` keeps handling signals, like
+    /// [`PanicPolicy::Continue`].
+    pub fn on_panic<F, P>(mut self, on_panic: F) -> Self
+    where
+        F: Fn(Box<dyn Any + Send>) -> P + Send + Sync + 'static,
+        P: Into<PanicPolicy>,
+    {
+        self.config.on_panic = Some(Arc::new(move |payload| on_panic(payload).into()));
+        self
+    }
+
+    /// See [`HandlerConfig::on_error`].
+    pub fn on_error<F>(mut self, on_error: F) -> Self
+    where
+        F: Fn(Error) + Send + Sync + 'static,
+    {
+        self.config.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// See [`HandlerConfig::forward_to`].
+    pub fn forward_to(mut self, pid: i32) -> Self {
+        self.config.forward_to = Some(pid);
+        self
+    }
+
+    /// See [`HandlerConfig::skip_if_no_tty`].
+    pub fn skip_if_no_tty(mut self, skip: bool) -> Self {
+        self.config.skip_if_no_tty = skip;
+        self
+    }
+
+    /// See [`HandlerConfig::ignore_sigpipe`].
+    pub fn ignore_sigpipe(mut self, ignore: bool) -> Self {
+        self.config.ignore_sigpipe = ignore;
+        self
+    }
+
+    /// See [`HandlerConfig::propagate_events`].
+    pub fn propagate_events(mut self, propagate: bool) -> Self {
+        self.config.propagate_events = propagate;
+        self
+    }
+
+    /// See [`HandlerConfig::debounce`].
+    pub fn debounce(mut self, window: Duration) -> Self {
+        self.config.debounce = Some(window);
+        self
+    }
+
+    /// See [`HandlerConfig::signal_hook`].
+    #[cfg(feature = "signal-hook")]
+    pub fn signal_hook(mut self, signal_hook: bool) -> Self {
+        self.config.signal_hook = signal_hook;
+        self
+    }
+
+    /// Register `user_handler` with the configured settings.
+    ///
+    /// Like with [`set_handler_with_signal()`](fn.set_handler_with_signal.html), the handler is
+    /// told the received signal, and returning `true` stops the signal handling thread.
+    ///
+    /// # Errors
+    /// Will return [`Error::NoSignals`] if an empty set of signals was configured,
+    /// [`Error::MultipleHandlers`] if a handler is already set, or an error if a system error
+    /// occurred while setting the handler or spawning the signal handling thread.
+    pub fn build<F>(self, user_handler: F) -> Result<JoinHandle<()>, Error>
+    where
+        F: FnMut(Signal) -> bool + 'static + Send,
+    {
+        let signals = self.signals.as_deref().unwrap_or(platform::DEFAULT_SIGNALS);
+        if signals.is_empty() {
+            return Err(Error::NoSignals);
+        }
+        init_and_register(
+            &self.config.signals(signals),
+            user_handler,
+            self.config.options(),
+            &self.config,
+        )
+        .map(|(handle, _)| handle)
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{
+    init_and_register, is_source, lock_init, platform, Error, HandlerConfig, ShutdownHandle,
+};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type AddedHandler = Arc<Mutex<Box<dyn FnMut() -> bool + Send>>>;
+
+/// Identifies a handler added with [`add_handler()`](fn.add_handler.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HandlerId(u64);
+
+/// Handlers added with [`add_handler()`], in registration order, and the registration serving
+/// them.
+struct Dispatcher {
+    next_id: u64,
+    handlers: Vec<(HandlerId, AddedHandler)>,
+    shutdown: Option<ShutdownHandle>,
+}
+
+static DISPATCHER: Mutex<Dispatcher> = Mutex::new(Dispatcher {
+    next_id: 0,
+    handlers: Vec::new(),
+    shutdown: None,
+});
+
+fn lock_dispatcher() -> MutexGuard<'static, Dispatcher> {
+    DISPATCHER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Dispatcher {
+    /// Returns whether the registration serving the handlers is still set.
+    fn is_registered(&self) -> bool {
+        match &self.shutdown {
+            Some(shutdown) => lock_init()
+                .as_ref()
+                .map_or(false, |current| is_source(&shutdown.source, current)),
+            None => false,
+        }
+    }
+
+    fn contains(&self, id: HandlerId) -> bool {
+        self.handlers.iter().any(|&(handler, _)| handler == id)
+    }
+
+    /// Remove the handler `id`, and unset the registration along with the last handler.
+    fn remove(&mut self, id: HandlerId) -> Result<(), Error> {
+        let position = self
+            .handlers
+            .iter()
+            .position(|&(handler, _)| handler == id)
+            .ok_or(Error::NoHandler)?;
+        self.handlers.remove(position);
+        if self.handlers.is_empty() {
+            if let Some(shutdown) = self.shutdown.take() {
+                shutdown.shutdown()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run the added handlers for a received signal, on the signal handling thread.
+fn dispatch() {
+    let handlers = lock_dispatcher().handlers.clone();
+    for (id, handler) in handlers {
+        // Removed by an earlier handler of this round.
+        if !lock_dispatcher().contains(id) {
+            continue;
+        }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            (handler.lock().unwrap_or_else(|e| e.into_inner()))()
+        }));
+        match result {
+            Ok(true) => {
+                if let Err(e) = lock_dispatcher().remove(id) {
+                    eprintln!("Error unsetting Ctrl-C handler: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(_) => eprintln!("Ctrl-C handler panicked, continuing to handle signals"),
+        }
+    }
+}
+
+/// Add a handler for Ctrl-C that runs alongside other handlers added this way.
+///
+/// Unlike [`set_handler()`](fn.set_handler.html), which allows only a single handler, any
+/// number of independent parts of a program, e.g. libraries, can add a handler. All of them
+/// share one registration and one signal handling thread, and run in registration order for
+/// each signal. Returning `true` from a handler removes it, like
+/// [`remove_handler()`](fn.remove_handler.html). The first added handler sets the
+/// registration, and removing the last one unsets it. Handlers may add and remove handlers
+/// themselves.
+///
+/// A panic in a handler is reported on stderr, and the other handlers still run.
+/// [`unset_handler()`](fn.unset_handler.html) removes all added handlers at once.
+///
+/// # Example
+/// ```no_run
+/// let id = ctrlc2::add_handler(|| {
+///     println!("Flushing the log");
+///     false
+/// })
+/// .expect("Error adding Ctrl-C handler");
+/// ctrlc2::add_handler(|| {
+///     println!("Closing connections");
+///     false
+/// })
+/// .expect("Error adding Ctrl-C handler");
+/// // ...
+/// ctrlc2::remove_handler(id).expect("Error removing Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler was set in any other way, or an error if
+/// a system error occurred while setting the registration.
+pub fn add_handler<F>(user_handler: F) -> Result<HandlerId, Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    let mut dispatcher = lock_dispatcher();
+    if !dispatcher.is_registered() {
+        // Unset with unset_handler() in the meantime, which removed the handlers.
+        dispatcher.handlers.clear();
+        let (_, source) = init_and_register(
+            platform::DEFAULT_SIGNALS,
+            |_| {
+                dispatch();
+                false
+            },
+            Default::default(),
+            &HandlerConfig::default(),
+        )?;
+        dispatcher.shutdown = Some(ShutdownHandle {
+            source: Arc::downgrade(&source),
+        });
+    }
+
+    let id = HandlerId(dispatcher.next_id);
+    dispatcher.next_id += 1;
+    dispatcher
+        .handlers
+        .push((id, Arc::new(Mutex::new(Box::new(user_handler)))));
+    Ok(id)
+}
+
+/// Remove a handler added with [`add_handler()`](fn.add_handler.html).
+///
+/// The handler doesn't run for signals received afterwards. Removing the last handler unsets
+/// the registration, and the signal handling thread exits.
+///
+/// # Errors
+/// Will return [`Error::NoHandler`] if the handler was already removed, or an error if a
+/// system error occurred while unsetting the registration.
+pub fn remove_handler(id: HandlerId) -> Result<(), Error> {
+    let mut dispatcher = lock_dispatcher();
+    if !dispatcher.is_registered() {
+        dispatcher.handlers.clear();
+        return Err(Error::NoHandler);
+    }
+    dispatcher.remove(id)
+}
@@ -8,15 +8,46 @@ pub enum Error {
     NoSuchSignal(crate::SignalType),
     /// Ctrl-C signal handler already registered.
     MultipleHandlers,
+    /// Another handler is already registered for the signal.
+    HandlerExists(crate::Signal),
+    /// A handler can't be installed for the signal on this platform.
+    Unsupported(crate::Signal),
+    /// No signals to handle were given.
+    NoSignals,
+    /// No signal handler is set.
+    NoHandler,
+    /// A signal name could not be parsed, see [`parse_signal()`](crate::parse_signal).
+    UnknownSignal(String),
     /// Unexpected system error.
     System(std::io::Error),
 }
 
 impl Error {
+    /// Returns whether this is a transient interruption of a blocking call, i.e. `EINTR`.
+    pub(crate) fn is_interrupted(&self) -> bool {
+        let e = match self {
+            Error::System(e) => e,
+            _ => return false,
+        };
+        #[cfg(unix)]
+        if let Some(errno) = e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<platform::Error>())
+        {
+            return *errno == platform::Error::EINTR;
+        }
+        e.kind() == std::io::ErrorKind::Interrupted
+    }
+
     fn describe(&self) -> &str {
         match *self {
             Error::NoSuchSignal(_) => "Signal could not be found from the system",
             Error::MultipleHandlers => "Ctrl-C signal handler already registered",
+            Error::HandlerExists(_) => "Another handler is already registered for the signal",
+            Error::Unsupported(_) => "Signal is not supported on this platform",
+            Error::NoSignals => "No signals to handle were given",
+            Error::NoHandler => "No signal handler is set",
+            Error::UnknownSignal(_) => "Unknown signal name",
             Error::System(_) => "Unexpected system error",
         }
     }
@@ -33,7 +64,7 @@ impl From<Error> for std::io::Error {
 
 impl From<platform::Error> for Error {
     fn from(e: platform::Error) -> Error {
-        #[cfg(not(windows))]
+        #[cfg(unix)]
         if e == platform::Error::EEXIST {
             return Error::MultipleHandlers;
         }
@@ -45,7 +76,19 @@ impl From<platform::Error> for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Ctrl-C error: {}", self.describe())
+        match self {
+            Error::UnknownSignal(name) => {
+                write!(f, "Ctrl-C error: {} {:?}", self.describe(), name)
+            }
+            Error::Unsupported(signal) => write!(
+                f,
+                "Ctrl-C error: {}: {}",
+                self.describe(),
+                platform::signal_name(*signal)
+            ),
+            Error::System(e) => write!(f, "Ctrl-C error: {}: {}", self.describe(), e),
+            _ => write!(f, "Ctrl-C error: {}", self.describe()),
+        }
     }
 }
 
@@ -54,7 +97,7 @@ impl std::error::Error for Error {
         self.describe()
     }
 
-    fn cause(&self) -> Option<&dyn std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             Error::System(ref e) => Some(e),
             _ => None,
@@ -0,0 +1,181 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::HandlerExit;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::panic;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::{Duration, Instant};
+
+/// Signal handling threads that are about to exit, notified on `EXITED_CHANGED`.
+///
+/// A thread is only finished for [`JoinHandle::is_finished()`] a moment after it announced
+/// its exit, so only the most recent exits need to be kept.
+static EXITED: Mutex<VecDeque<ThreadId>> = Mutex::new(VecDeque::new());
+static EXITED_CHANGED: Condvar = Condvar::new();
+const EXITED_KEPT: usize = 16;
+
+/// Announce that the calling signal handling thread is about to exit, see
+/// [`join_timeout()`](fn.join_timeout.html).
+pub(crate) fn announce_exit() {
+    let mut exited = EXITED.lock().unwrap_or_else(|e| e.into_inner());
+    if exited.len() == EXITED_KEPT {
+        exited.pop_front();
+    }
+    exited.push_back(thread::current().id());
+    EXITED_CHANGED.notify_all();
+}
+
+/// Error returned by [`join_timeout()`](fn.join_timeout.html) if the thread didn't finish in
+/// time.
+pub struct JoinTimeout<T> {
+    handle: JoinHandle<T>,
+}
+
+impl<T> JoinTimeout<T> {
+    /// Returns the handle of the thread that is still running, e.g. to wait for it again.
+    pub fn into_handle(self) -> JoinHandle<T> {
+        self.handle
+    }
+}
+
+impl<T> fmt::Debug for JoinTimeout<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinTimeout")
+            .field("thread", self.handle.thread())
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for JoinTimeout<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Timed out waiting for the Ctrl-C handling thread")
+    }
+}
+
+impl<T> std::error::Error for JoinTimeout<T> {}
+
+/// Future resolving to why the signal handling thread stopped, once it has finished, see
+/// [`set_handler_with_completion()`](fn.set_handler_with_completion.html).
+///
+/// It is fired by the signal handling thread itself, after the handler has been dropped, so it
+/// works with any async runtime and never blocks the executor.
+#[derive(Debug)]
+pub struct Completion {
+    state: Arc<Mutex<CompletionState>>,
+}
+
+#[derive(Debug, Default)]
+struct CompletionState {
+    exit: Option<HandlerExit>,
+    waker: Option<Waker>,
+}
+
+/// Sending half of a [`Completion`], fired on the signal handling thread.
+pub(crate) struct Completer {
+    state: Arc<Mutex<CompletionState>>,
+}
+
+impl Completion {
+    pub(crate) fn new() -> (Completion, Completer) {
+        let state = Arc::new(Mutex::new(CompletionState::default()));
+        let completer = Completer {
+            state: Arc::clone(&state),
+        };
+        (Completion { state }, completer)
+    }
+
+    /// Returns whether the signal handling thread has finished, so awaiting resolves right away.
+    pub fn is_finished(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .exit
+            .is_some()
+    }
+}
+
+impl Future for Completion {
+    type Output = HandlerExit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<HandlerExit> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.exit {
+            Some(exit) => Poll::Ready(exit),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Completer {
+    pub(crate) fn complete(self, exit: HandlerExit) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.exit = Some(exit);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Wait at most `timeout` for the signal handling thread of `handle` to finish.
+///
+/// [`JoinHandle::join()`] blocks indefinitely, which doesn't suit shutdowns that should
+/// proceed after a while regardless, e.g. together with
+/// [`set_handler_with_force_exit()`](fn.set_handler_with_force_exit.html). The signal handling
+/// threads of this crate announce their exit, so this sleeps until then instead of polling.
+/// Other threads are only seen to finish by the time `timeout` has passed.
+///
+/// Returns the value the thread returned. A panic of the thread, which the signal handling
+/// threads of this crate never propagate from the handler, is resumed on the calling thread.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// // ...
+/// if ctrlc2::join_timeout(handle, std::time::Duration::from_secs(5)).is_err() {
+///     eprintln!("Cleanup didn't finish in time, exiting anyway");
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`JoinTimeout`], which gives back the handle, if the thread didn't finish in
+/// time.
+pub fn join_timeout<T>(handle: JoinHandle<T>, timeout: Duration) -> Result<T, JoinTimeout<T>> {
+    let deadline = Instant::now() + timeout;
+    let id = handle.thread().id();
+    let mut exited = EXITED.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        if handle.is_finished() || exited.contains(&id) {
+            break;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(JoinTimeout { handle });
+        }
+        exited = EXITED_CHANGED
+            .wait_timeout(exited, remaining)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+    exited.retain(|&exited| exited != id);
+    drop(exited);
+
+    // The thread has announced its exit, so this only waits for it to return.
+    Ok(handle
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload)))
+}
@@ -0,0 +1,152 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{platform, record_received, Error, Signal};
+use nix::errno::Errno;
+use nix::libc;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr;
+
+/// Ctrl-C received through a BSD `kqueue`, see [`register_kqueue()`](fn.register_kqueue.html).
+///
+/// The previous signal dispositions are restored when this is dropped.
+pub struct KqueueSource {
+    kq: OwnedFd,
+    previous: platform::Disposition,
+}
+
+impl KqueueSource {
+    /// Block until Ctrl-C is received and return the received signal.
+    ///
+    /// # Errors
+    /// Will return an error if a system error occurred.
+    pub fn recv(&self) -> Result<Signal, Error> {
+        loop {
+            if let Some(signal) = self.next_event(ptr::null())? {
+                return Ok(signal);
+            }
+        }
+    }
+
+    /// Return the received signal if Ctrl-C has been received, without blocking.
+    ///
+    /// Returns `Ok(None)` if no signal is pending.
+    ///
+    /// # Errors
+    /// Will return an error if a system error occurred.
+    pub fn try_recv(&self) -> Result<Option<Signal>, Error> {
+        let timeout = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        self.next_event(&timeout)
+    }
+
+    /// Wait for one event for at most `timeout`, or forever if it is null.
+    fn next_event(&self, timeout: *const libc::timespec) -> Result<Option<Signal>, Error> {
+        loop {
+            let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+            let n = unsafe {
+                libc::kevent(self.kq.as_raw_fd(), ptr::null(), 0, &mut event, 1, timeout)
+            };
+            match n {
+                -1 if Errno::last() == Errno::EINTR => {}
+                -1 => return Err(Errno::last().into()),
+                0 => return Ok(None),
+                _ => {
+                    let signal = Signal::try_from(event.ident as libc::c_int)?;
+                    // The number of times the signal arrived since the last event.
+                    record_received(signal, (event.data as u64).max(1));
+                    return Ok(Some(signal));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for KqueueSource {
+    fn drop(&mut self) {
+        if let Err(e) = unsafe { platform::restore_disposition(&self.previous) } {
+            eprintln!("Error restoring the signal dispositions: {}", e);
+        }
+    }
+}
+
+impl AsRawFd for KqueueSource {
+    /// Returns the `kqueue`, which is readable whenever a signal is pending.
+    ///
+    /// Add it to another `kqueue` with `EVFILT_READ`, or poll it for readability, and receive
+    /// the signal with [`try_recv()`](#method.try_recv) or [`recv()`](#method.recv).
+    fn as_raw_fd(&self) -> RawFd {
+        self.kq.as_raw_fd()
+    }
+}
+
+/// Receive Ctrl-C through a `kqueue` instead of a signal handler.
+///
+/// The signals are the same as for [`set_handler()`](fn.set_handler.html). Each is registered
+/// with an `EVFILT_SIGNAL` filter on a new `kqueue(2)`, so no code runs in signal context at
+/// all, and no signal handling thread is needed. The returned [`KqueueSource`] can be waited on
+/// directly or integrated into an existing `kqueue` event loop.
+///
+/// `EVFILT_SIGNAL` only observes signals, it doesn't keep them from being delivered. The
+/// dispositions of the signals are therefore set to `SIG_IGN` until the source is dropped.
+/// Don't combine it with the other handlers of this crate, which replace the dispositions.
+/// Child processes created via `execve(2)` inherit ignored signals, so reset them in the
+/// children if needed.
+///
+/// Only available on macOS, iOS and the BSDs with the `kqueue` feature.
+///
+/// # Example
+/// ```no_run
+/// let signals = ctrlc2::register_kqueue().expect("Error setting up kqueue");
+/// let signal = signals.recv().expect("Error receiving Ctrl-C");
+/// println!("Got {:?}! Exiting...", signal);
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while creating the `kqueue`, registering the
+/// signals or ignoring them.
+pub fn register_kqueue() -> Result<KqueueSource, Error> {
+    let kq = unsafe { libc::kqueue() };
+    if kq < 0 {
+        return Err(Errno::last().into());
+    }
+    let kq = unsafe { OwnedFd::from_raw_fd(kq) };
+    if unsafe { libc::fcntl(kq.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+        return Err(Errno::last().into());
+    }
+
+    let changes: Vec<libc::kevent> = platform::DEFAULT_SIGNALS
+        .iter()
+        .map(|&signal| {
+            let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+            event.ident = signal as libc::uintptr_t;
+            event.filter = libc::EVFILT_SIGNAL;
+            event.flags = libc::EV_ADD;
+            event
+        })
+        .collect();
+    let n = unsafe {
+        libc::kevent(
+            kq.as_raw_fd(),
+            changes.as_ptr(),
+            changes.len() as _,
+            ptr::null_mut(),
+            0,
+            ptr::null(),
+        )
+    };
+    if n < 0 {
+        return Err(Errno::last().into());
+    }
+
+    let previous = unsafe { platform::ignore_saving(platform::DEFAULT_SIGNALS)? };
+    Ok(KqueueSource { kq, previous })
+}
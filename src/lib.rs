@@ -19,7 +19,8 @@
 //!
 //! Setting a handler will start a new dedicated signal handling thread where we
 //! execute the handler each time we receive a `Ctrl+C` signal. There can only be
-//! one handler, you would typically set one at the start of your program.
+//! one handler, you would typically set one at the start of your program. The handler can
+//! be removed again with [unset_handler()](fn.unset_handler.html).
 //!
 //! # Example
 //! ```no_run
@@ -46,23 +47,146 @@
 //! # Handling SIGTERM and SIGHUP
 //! Handling of `SIGTERM and SIGHUP` can be enabled with `termination` feature. If this is enabled,
 //! the handler specified by `set_handler()` will be executed for `SIGINT`, `SIGTERM` and `SIGHUP`.
+//! To handle a different set of signals, use [set_handler_for()](fn.set_handler_for.html).
+//!
+//! On Windows, the `termination` feature makes the handler execute for the `CTRL_CLOSE_EVENT`,
+//! `CTRL_LOGOFF_EVENT` and `CTRL_SHUTDOWN_EVENT` console control events as well, which are sent
+//! when the console window is closed, the user logs off or the system shuts down. Windows
+//! terminates the process once the handler routine returns, so the routine waits for the
+//! handler to return, but only for up to five seconds, after which Windows forcibly terminates
+//! the process anyway. Keep the cleanup short, and don't rely on anything running after the
+//! handler returns.
+//!
+//! # Handling SIGQUIT
+//! Handling of `SIGQUIT`, usually sent with `Ctrl+\`, can be enabled with the `quit` feature,
+//! independently of the `termination` feature. The handler is then executed for `SIGQUIT` as
+//! well, and handlers that are told the received signal see `SIGQUIT`. Windows has no
+//! equivalent, so the feature does nothing there.
 //!
 
 #![macro_use]
 
+/// Emit a [`tracing`](https://docs.rs/tracing) event if the `tracing` feature is enabled.
+macro_rules! trace {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+mod config;
+pub use config::{Builder, ErrorCallback, HandlerConfig, PanicCallback, PanicPolicy};
+mod dispatch;
+pub use dispatch::{add_handler, remove_handler, HandlerId};
 mod error;
+mod join;
+pub use join::{join_timeout, Completion, JoinTimeout};
 mod platform;
 pub use platform::Signal;
+#[cfg(windows)]
+pub use platform::WindowsEvent;
+#[cfg(unix)]
+pub use platform::{SigInfo, SignalOrigin};
 mod signal;
 pub use signal::*;
+mod sink;
+pub use sink::*;
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+mod async_handler;
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+pub use async_handler::*;
+mod raw;
+pub use raw::*;
+#[cfg(all(target_os = "linux", feature = "signalfd"))]
+mod signalfd;
+#[cfg(all(target_os = "linux", feature = "signalfd"))]
+pub use signalfd::*;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod realtime;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use realtime::*;
+#[cfg(all(unix, feature = "sigwait"))]
+mod sigwait;
+#[cfg(all(unix, feature = "sigwait"))]
+pub use sigwait::*;
+#[cfg(all(
+    feature = "kqueue",
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly"
+    )
+))]
+mod kqueue;
+#[cfg(all(
+    feature = "kqueue",
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly"
+    )
+))]
+pub use kqueue::*;
+#[cfg(feature = "stream")]
+mod stream;
+#[cfg(feature = "stream")]
+pub use stream::*;
 
 pub use error::Error;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
 use std::thread::{self, JoinHandle};
 
+/// Whether a handler is set, for checks without taking `INIT_LOCK`.
+///
+/// Only written with `INIT_LOCK` held, together with the registration. Under the lock, the
+/// registration itself decides whether a handler is set, so concurrent registrations can't
+/// both succeed.
 static INIT: AtomicBool = AtomicBool::new(false);
-static INIT_LOCK: Mutex<()> = Mutex::new(());
+static INIT_LOCK: Mutex<Option<Arc<platform::Source>>> = Mutex::new(None);
+static SIGNALS_RECEIVED: AtomicU64 = AtomicU64::new(0);
+/// Raw number of the last received signal, or `NO_SIGNAL`.
+static LAST_SIGNAL: AtomicI32 = AtomicI32::new(NO_SIGNAL);
+const NO_SIGNAL: i32 = -1;
+static PAUSED: AtomicBool = AtomicBool::new(false);
+/// Notified with `INIT_LOCK` held when signal delivery is resumed or a handler is unset.
+static RESUMED: Condvar = Condvar::new();
+static REPLACEMENT: Mutex<Option<Replacement>> = Mutex::new(None);
+static SHARED: Mutex<Option<Shared>> = Mutex::new(None);
+/// Signal handling thread spawned for `.0`, see [`handler_thread_id()`].
+static HANDLER_THREAD: Mutex<Option<(Weak<platform::Source>, ThreadHandle)>> = Mutex::new(None);
+/// Handler set with [`set_handler_idempotent()`], locked before `INIT_LOCK`.
+static IDEMPOTENT: Mutex<Option<(Weak<platform::Source>, SharedJoinHandle)>> = Mutex::new(None);
+
+/// Handler set with [`set_shared_handler()`] for the signal handling thread of `source`.
+struct Shared {
+    source: Weak<platform::Source>,
+    cell: Arc<Mutex<SharedHandler>>,
+}
+
+/// Handler passed to [`replace_handler()`] for the signal handling thread of `source`.
+struct Replacement {
+    source: Weak<platform::Source>,
+    user_handler: Option<Box<dyn FnMut() -> bool + Send>>,
+}
+
+/// Lock `INIT_LOCK`, recovering from a panic of a previous holder.
+///
+/// The registration is always updated together with `INIT` and never left half-updated, so it
+/// is consistent even if the lock is poisoned.
+fn lock_init() -> MutexGuard<'static, Option<Arc<platform::Source>>> {
+    INIT_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
 
 /// Register signal handler for Ctrl-C.
 ///
@@ -76,116 +200,2561 @@ static INIT_LOCK: Mutex<()> = Mutex::new(());
 ///
 /// # Warning
 /// On Unix, the handler registration for `SIGINT`, (`SIGTERM` and `SIGHUP` if termination feature
-/// is enabled) or `SA_SIGINFO` posix signal handlers will be overwritten. On Windows, multiple
-/// handler routines are allowed, but they are called on a last-registered, first-called basis
-/// until the signal is handled.
+/// is enabled, `SIGQUIT` if quit feature is enabled) or `SA_SIGINFO` posix signal handlers
+/// will be overwritten. On Windows, multiple handler routines are allowed, but they are called
+/// on a last-registered, first-called basis until the signal is handled. A routine registered
+/// after ours that handles the event keeps ours from seeing it, see
+/// `windows_handler_reachable()`. Ours handles the events it receives, unless
+/// [`HandlerConfig::propagate_events`] is set.
 ///
 /// ctrlc2::try_set_handler will error (on Unix) if another signal handler exists for the same
-/// signal(s) that ctrlc2 is trying to attach the handler to.
+/// signal(s) that ctrlc2 is trying to attach the handler to. To share the signals with handlers
+/// registered through the `signal-hook` crate instead, enable the `signal-hook` feature and set
+/// `HandlerConfig::signal_hook`.
+///
+/// On Unix, the signal handlers are installed with `SA_RESTART`, so system calls interrupted by
+/// the signals are restarted instead of failing with `EINTR`. Use
+/// [`HandlerConfig::restart_syscalls`] to change this.
 ///
 /// On Unix, signal dispositions and signal handlers are inherited by child processes created via
-/// `fork(2)` on, but not by child processes created via `execve(2)`.
+/// `fork(2)` on, but not by child processes created via `execve(2)`. The signal handling thread
+/// doesn't exist in a forked child, see [`reinit_after_fork()`](fn.reinit_after_fork.html).
 /// Signal handlers are not inherited on Windows.
 ///
+/// On Unix, the handler always runs on the signal handling thread, whichever thread the os
+/// delivers the signal to. The signal handling thread unblocks the signals for itself, so
+/// blocking them with `pthread_sigmask(3)` on all other threads routes them to it.
+///
 /// # Errors
 /// Will return an error if a system error occurred while setting the handler.
 ///
 /// # Panics
-/// Any panic in the handler will not be caught and will cause the signal handler thread to stop.
-pub fn set_handler<F>(user_handler: F) -> Result<JoinHandle<()>, Error>
+/// A panic in the handler is caught and reported on stderr, and the signal handling thread keeps
+/// handling signals. Use [`set_handler_with_config()`](fn.set_handler_with_config.html) with
+/// [`HandlerConfig::on_panic`] to handle panics differently.
+pub fn set_handler<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
 where
     F: FnMut() -> bool + 'static + Send,
 {
-    init_and_set_handler(user_handler, true)
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler(),
+        Default::default(),
+    )
 }
 
-/// The same as ctrlc2::set_handler but errors if a handler already exists for the signal(s).
+/// Why the signal handling thread of
+/// [`set_handler_with_exit()`](fn.set_handler_with_exit.html) stopped, as returned by joining it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerExit {
+    /// The handler returned `true`.
+    Completed,
+    /// The handler panicked. The panic itself is reported by the panic hook, on stderr by
+    /// default.
+    Panicked,
+    /// The handler was unset, e.g. with [`unset_handler()`](fn.unset_handler.html), before it
+    /// returned `true`.
+    Unregistered,
+    /// A fatal system error occurred while waiting for a signal. The error is reported on
+    /// stderr.
+    Failed,
+}
+
+/// Register signal handler for Ctrl-C whose thread tells why it stopped.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but joining the returned handle yields a
+/// [`HandlerExit`], which allows logging the reason for the exit. Unlike with `set_handler()`,
+/// a panic in the handler ends the signal handling thread with [`HandlerExit::Panicked`]. The
+/// handler stays set in that case, so unset it before setting a new one.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler_with_exit(|| true).expect("Error setting Ctrl-C handler");
+/// match handle.join().unwrap() {
+///     ctrlc2::HandlerExit::Completed => println!("Shutting down"),
+///     exit => eprintln!("Ctrl-C handling stopped: {:?}", exit),
+/// }
+/// ```
 ///
 /// # Errors
-/// Will return an error if another handler exists or if a system error occurred while setting the
-/// handler.
-pub fn try_set_handler<F>(user_handler: F) -> Result<JoinHandle<()>, Error>
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_exit<F>(mut user_handler: F) -> Result<JoinHandle<HandlerExit>, Error>
 where
     F: FnMut() -> bool + 'static + Send,
 {
-    init_and_set_handler(user_handler, false)
+    init_and_register_with_exit(
+        platform::DEFAULT_SIGNALS,
+        move |_: Signal| user_handler(),
+        Default::default(),
+        &HandlerConfig::default(),
+        true,
+        |exit| exit,
+    )
+    .map(|(handle, _)| handle)
 }
 
-fn init_and_set_handler<F>(user_handler: F, overwrite: bool) -> Result<JoinHandle<()>, Error>
+/// Register signal handler for Ctrl-C whose exit can be awaited.
+///
+/// Works like [`set_handler_with_exit()`](fn.set_handler_with_exit.html), but instead of a
+/// `JoinHandle`, a [`Completion`] is returned, a future that resolves to the [`HandlerExit`]
+/// once the signal handling thread has finished, after the handler has been dropped. Awaiting
+/// it doesn't block the executor, unlike calling `join()` from async code, and it doesn't need
+/// a particular async runtime.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// let completion = ctrlc2::set_handler_with_completion(|| {
+///     println!("Shutting down");
+///     true
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// let exit = completion.await;
+/// println!("Ctrl-C handling stopped: {:?}", exit);
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_completion<F>(mut user_handler: F) -> Result<Completion, Error>
 where
     F: FnMut() -> bool + 'static + Send,
 {
-    if !INIT.load(Ordering::Acquire) {
-        let _guard = INIT_LOCK.lock().unwrap();
+    let (completion, completer) = Completion::new();
+    init_and_register_with_exit(
+        platform::DEFAULT_SIGNALS,
+        move |_: Signal| user_handler(),
+        Default::default(),
+        &HandlerConfig::default(),
+        true,
+        move |exit| completer.complete(exit),
+    )?;
+    Ok(completion)
+}
 
-        if !INIT.load(Ordering::Relaxed) {
-            let handle = set_handler_inner(user_handler, overwrite)?;
-            INIT.store(true, Ordering::Release);
-            return Ok(handle);
-        }
+/// Register signal handler for Ctrl-C that owns some state.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but `state` is moved to the signal
+/// handling thread and passed to the handler by mutable reference on each signal. State that is
+/// only touched by the handler therefore needs no `Arc<Mutex<_>>`.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_with_state(Vec::new(), |steps: &mut Vec<&str>| {
+///     steps.push("flush");
+///     println!("Shutdown steps so far: {:?}", steps);
+///     steps.len() == 3
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_state<T, F>(
+    mut state: T,
+    mut user_handler: F,
+) -> Result<JoinHandle<()>, Error>
+where
+    T: Send + 'static,
+    F: FnMut(&mut T) -> bool + 'static + Send,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler(&mut state),
+        Default::default(),
+    )
+}
+
+/// Register signal handler for Ctrl-C that is told how many signals were received.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but signals received while the handler
+/// is busy are not handled one by one. Instead, the handler runs once for all of them and is
+/// passed their number, which is at least 1. This allows e.g. forcing the exit when Ctrl-C is
+/// pressed three times, however fast. Signals received while delivery is
+/// [paused](fn.pause.html) are handled at once on resume as well.
+///
+/// On Windows, every console control event is counted. On Unix, the kernel merges a signal that
+/// arrives while the same signal is already pending, which only happens within a few
+/// instructions of the os handler running. Beyond that, every signal is counted as well.
+///
+/// # Example
+/// ```no_run
+/// let mut presses = 0;
+/// ctrlc2::set_handler_with_count(move |count| {
+///     presses += count;
+///     if presses >= 3 {
+///         std::process::exit(1);
+///     }
+///     println!("Shutting down, press Ctrl-C {} more times to force", 3 - presses);
+///     false
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_count<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(u32) -> bool + 'static + Send,
+{
+    init_and_register_for(
+        platform::DEFAULT_SIGNALS,
+        move |batch: Batch| user_handler(batch.count),
+        Default::default(),
+        &HandlerConfig::default(),
+    )
+    .map(|(handle, _)| handle)
+}
+
+/// Register signal handler for Ctrl-C that tells with [`ControlFlow`] whether to keep going.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but instead of a `bool` whose meaning is
+/// easily mixed up, the handler returns [`ControlFlow::Continue`] to keep handling signals and
+/// [`ControlFlow::Break`] to stop the signal handling thread.
+///
+/// # Example
+/// ```no_run
+/// use std::ops::ControlFlow;
+///
+/// ctrlc2::set_handler_flow(|| {
+///     println!("Hello world!");
+///     ControlFlow::Continue(())
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_flow<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() -> ControlFlow<()> + 'static + Send,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler().is_break(),
+        Default::default(),
+    )
+}
+
+/// Register signal handler for Ctrl-C that stays armed until it is unset.
+///
+/// Unlike with [`set_handler()`](fn.set_handler.html), whose handler stops the signal handling
+/// thread by returning `true`, the handler returns nothing, and the thread never exits on its
+/// own: every signal runs the handler, also after it did something special for one of them.
+/// Only [`unset_handler()`](fn.unset_handler.html) or a
+/// [`ShutdownHandle`](struct.ShutdownHandle.html) stop it, after which the `JoinHandle` can be
+/// joined.
+///
+/// # Example
+/// ```no_run
+/// let mut presses = 0;
+/// ctrlc2::set_handler_persistent(move || {
+///     presses += 1;
+///     if presses == 1 {
+///         println!("Press Ctrl-C again to see this handler run again");
+///     }
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_persistent<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() + 'static + Send,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            user_handler();
+            false
+        },
+        Default::default(),
+    )
+}
+
+/// Cloneable handle to the signal handling thread, see
+/// [`set_handler_idempotent()`](fn.set_handler_idempotent.html).
+#[derive(Debug, Clone)]
+pub struct SharedJoinHandle {
+    handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+    thread: ThreadHandle,
+}
+
+impl SharedJoinHandle {
+    /// Returns the signal handling thread.
+    pub fn thread(&self) -> ThreadHandle {
+        self.thread
     }
 
-    Err(Error::MultipleHandlers)
+    /// Returns whether the signal handling thread has finished.
+    pub fn is_finished(&self) -> bool {
+        self.handle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map_or(true, JoinHandle::is_finished)
+    }
+
+    /// Wait for the signal handling thread to finish.
+    ///
+    /// Any number of clones can wait at the same time. Only the first call joins the thread and
+    /// gets the result of [`JoinHandle::join()`], the others wait for that and return `Ok(())`.
+    ///
+    /// # Errors
+    /// Returns the panic payload if the signal handling thread panicked.
+    pub fn join(&self) -> thread::Result<()> {
+        let mut handle = self.handle.lock().unwrap_or_else(|e| e.into_inner());
+        match handle.take() {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
 }
 
-fn set_handler_inner<F>(mut user_handler: F, overwrite: bool) -> Result<JoinHandle<()>, Error>
+/// Register signal handler for Ctrl-C, or return the handle of the one already set by this
+/// function.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but can be called any number of times,
+/// e.g. from setup code that might run more than once. Only the first call sets the handler,
+/// and its `user_handler` is the one that stays active: later calls drop theirs and return a
+/// clone of the same [`SharedJoinHandle`]. Once the handler is unset, the next call sets a new
+/// one.
+///
+/// # Example
+/// ```no_run
+/// fn setup() -> ctrlc2::SharedJoinHandle {
+///     ctrlc2::set_handler_idempotent(|| true).expect("Error setting Ctrl-C handler")
+/// }
+///
+/// let handle = setup();
+/// let again = setup();
+/// assert_eq!(handle.thread(), again.thread());
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is set by another function of this
+/// crate, or an error if a system error occurred while setting the handler.
+pub fn set_handler_idempotent<F>(mut user_handler: F) -> Result<SharedJoinHandle, Error>
 where
     F: FnMut() -> bool + 'static + Send,
 {
-    unsafe { platform::init_os_handler(overwrite)? };
+    let mut idempotent = IDEMPOTENT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((source, handle)) = idempotent.as_ref() {
+        let current = lock_init()
+            .as_ref()
+            .map_or(false, |current| source.as_ptr() == Arc::as_ptr(current));
+        if current {
+            return Ok(handle.clone());
+        }
+    }
 
-    let builder = thread::Builder::new()
-        .name("ctrl-c".into())
-        .spawn(move || loop {
-            unsafe {
-                platform::block_ctrl_c().expect("Critical system error while waiting for Ctrl-C");
-            }
-            if user_handler() {
-                break;
-            }
-        })
-        .map_err(Error::System)?;
+    let (handle, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler(),
+        Default::default(),
+        &HandlerConfig::default(),
+    )?;
+    let shared = SharedJoinHandle {
+        thread: ThreadHandle::new(&handle),
+        handle: Arc::new(Mutex::new(Some(handle))),
+    };
+    *idempotent = Some((Arc::downgrade(&source), shared.clone()));
+    Ok(shared)
+}
 
-    Ok(builder)
+/// Register signal handler for Ctrl-C whose cleanup can fail.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but the handler returns a `Result`.
+/// `Ok(true)` stops the signal handling thread and `Ok(false)` keeps it going, like the `bool`
+/// returned by the handler of `set_handler()`. An error is passed to `on_error` on the signal
+/// handling thread, which logs or forwards it and returns whether to stop the thread as well.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_result(
+///     || {
+///         std::fs::write("state.json", "{}")?;
+///         Ok(true)
+///     },
+///     |e: std::io::Error| {
+///         eprintln!("Error saving state: {}", e);
+///         false
+///     },
+/// )
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_result<F, E, G>(
+    mut user_handler: F,
+    mut on_error: G,
+) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() -> Result<bool, E> + 'static + Send,
+    G: FnMut(E) -> bool + 'static + Send,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler().unwrap_or_else(&mut on_error),
+        Default::default(),
+    )
 }
 
-/// Register signal handler in tokio runtime for Ctrl-C.
-#[cfg(feature = "tokio")]
-pub async fn set_async_handler<F>(user_handler: F) -> tokio::task::JoinHandle<()>
+/// Register signal handler for Ctrl-C that gives feedback while the handler is busy.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but `on_busy` is called on the signal
+/// handling thread for every signal received while the handler hasn't returned yet, e.g. to
+/// tell a user pressing Ctrl-C repeatedly that the shutdown is in progress. For this, the
+/// handler runs on a worker thread of its own, named `ctrl-c-worker`, and signals arriving
+/// while it is busy are not queued for it. The signal handling thread waits for the worker
+/// before it exits, so joining the returned `JoinHandle` waits for the handler as well.
+///
+/// On Windows, the process may be terminated once the signal handling thread has passed a
+/// close, logoff or shutdown event on to the worker, without waiting for the handler.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_with_busy(
+///     || {
+///         println!("Flushing to disk...");
+///         true
+///     },
+///     || println!("Shutting down, please wait..."),
+/// )
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler or spawning the
+/// worker thread.
+pub fn set_handler_with_busy<F, B>(
+    mut user_handler: F,
+    mut on_busy: B,
+) -> Result<JoinHandle<()>, Error>
 where
-    F: std::future::Future<Output = ()> + 'static + Send,
+    F: FnMut() -> bool + 'static + Send,
+    B: FnMut() + 'static + Send,
 {
-    tokio::spawn(async move {
-        let block = async move {
-            #[cfg(unix)]
-            {
-                #[cfg(not(feature = "termination"))]
-                tokio::signal::ctrl_c().await?;
+    let busy = Arc::new(AtomicBool::new(false));
+    let worker_busy = Arc::clone(&busy);
+    let (tx, rx) = mpsc::channel::<()>();
+    let worker_slot = Arc::new(Mutex::new(None::<JoinHandle<()>>));
+    let thread_worker = Arc::clone(&worker_slot);
+    // Keep the signal handling thread from exiting until it knows the worker to join.
+    let mut slot = worker_slot.lock().unwrap();
+    let (handle, source) = init_and_register_with_exit(
+        platform::DEFAULT_SIGNALS,
+        move |_: Signal| {
+            if busy.swap(true, Ordering::AcqRel) {
+                on_busy();
+                false
+            } else {
+                // The worker is gone once the handler returned `true`.
+                tx.send(()).is_err()
+            }
+        },
+        Default::default(),
+        &HandlerConfig::default(),
+        false,
+        move |_| {
+            // The worker stops once the handler and with it `tx` were dropped.
+            let worker = thread_worker.lock().unwrap().take();
+            if let Some(worker) = worker {
+                let _ = worker.join();
+            }
+        },
+    )?;
 
-                #[cfg(feature = "termination")]
-                {
-                    use tokio::signal::unix::{signal, SignalKind};
-                    let mut kill_signal = signal(SignalKind::terminate())?;
-                    let mut int_signal = signal(SignalKind::interrupt())?;
-                    let mut hup_signal = signal(SignalKind::hangup())?;
-                    tokio::select! {
-                        _ = tokio::signal::ctrl_c() => {},
-                        _ = kill_signal.recv() => {},
-                        _ = int_signal.recv() => {},
-                        _ = hup_signal.recv() => {}
+    let shutdown = ShutdownHandle {
+        source: Arc::downgrade(&source),
+    };
+    let worker_shutdown = shutdown.clone();
+    let worker = thread::Builder::new()
+        .name("ctrl-c-worker".into())
+        .spawn(move || {
+            let config = HandlerConfig::default();
+            for () in rx {
+                match panic::catch_unwind(AssertUnwindSafe(&mut user_handler)) {
+                    Ok(true) => {
+                        if let Err(e) = worker_shutdown.shutdown() {
+                            eprintln!("Error unsetting Ctrl-C handler: {}", e);
+                        }
+                        break;
                     }
+                    Ok(false) => {}
+                    Err(payload) => match config.report_panic(payload) {
+                        PanicPolicy::Continue => {}
+                        PanicPolicy::Stop => {
+                            if let Err(e) = worker_shutdown.shutdown() {
+                                eprintln!("Error unsetting Ctrl-C handler: {}", e);
+                            }
+                            break;
+                        }
+                        PanicPolicy::Exit(code) => std::process::exit(code),
+                    },
                 }
+                worker_busy.store(false, Ordering::Release);
             }
+        });
+    match worker {
+        Ok(worker) => *slot = Some(worker),
+        Err(e) => {
+            drop(slot);
+            let _ = shutdown.shutdown();
+            return Err(Error::System(e));
+        }
+    }
+    Ok(handle)
+}
+
+/// Register signal handler for Ctrl-C that is told which signal was received.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but the handler receives the
+/// [`Signal`] that woke the signal handling thread. On Unix this is
+/// `SIGINT`, or `SIGTERM` and `SIGHUP` if the termination feature is enabled, or `SIGQUIT` if
+/// the quit feature is enabled. On Windows this
+/// is the console control event, `CTRL_C_EVENT` or `CTRL_BREAK_EVENT`, or `CTRL_CLOSE_EVENT`,
+/// `CTRL_LOGOFF_EVENT` and `CTRL_SHUTDOWN_EVENT` if the termination feature is enabled.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_with_signal(|signal| {
+///     println!("Received {:?}", signal);
+///     true
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_signal<F>(user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    init_and_set_handler(platform::DEFAULT_SIGNALS, user_handler, Default::default())
+}
 
-            #[cfg(windows)]
-            tokio::signal::ctrl_c().await?;
+/// Register signal handler for the given signals only.
+///
+/// Works like [`set_handler_with_signal()`](fn.set_handler_with_signal.html), but instead of
+/// the default set of signals (which depends on the termination and quit features) exactly the
+/// given signals are handled. All other signals keep their current disposition.
+///
+/// On Unix, any signal that can be caught may be given, e.g. `SIGUSR1` and `SIGUSR2`, and the
+/// handler is told which one fired. On Windows, only console control events are meaningful.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(unix)]
+/// ctrlc2::set_handler_for(&[ctrlc2::Signal::SIGUSR1, ctrlc2::Signal::SIGUSR2], |signal| {
+///     match signal {
+///         ctrlc2::Signal::SIGUSR1 => println!("Reloading configuration"),
+///         _ => println!("Rotating logs"),
+///     }
+///     false
+/// })
+/// .expect("Error setting signal handler");
+/// ```
+///
+/// # Errors
+/// Will return [`Error::NoSignals`] if `signals` is empty, [`Error::Unsupported`] if one of
+/// them can't be handled on this platform, e.g. `SIGKILL`, or an error if a system error
+/// occurred while setting the handler.
+pub fn set_handler_for<F>(signals: &[Signal], user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    if signals.is_empty() {
+        return Err(Error::NoSignals);
+    }
+    init_and_set_handler(signals, user_handler, Default::default())
+}
 
-            user_handler.await;
+/// Closure run for a particular signal, see [`set_handlers()`](fn.set_handlers.html).
+pub type SignalHandler = Box<dyn FnMut() -> bool + Send>;
 
-            Ok::<(), std::io::Error>(())
-        };
-        if let Err(err) = block.await {
-            eprintln!("Critical system error while waiting for Ctrl-C: {}", err);
-        }
+/// Register a distinct handler for each of the given signals.
+///
+/// Works like [`set_handler_for()`](fn.set_handler_for.html) for the keys of `handlers`, but
+/// each received signal runs the handler registered for it, so no `match` on the signal is
+/// needed. Returning `true` from any of the handlers stops handling all of the signals.
+///
+/// On Windows, the keys are console control events, so e.g. `CTRL_C_EVENT` and
+/// `CTRL_BREAK_EVENT` get handlers of their own, like a graceful stop on Ctrl+C and a state dump
+/// on Ctrl+Break.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(unix)]
+/// # {
+/// use ctrlc2::{Signal, SignalHandler};
+/// use std::collections::HashMap;
+///
+/// let mut handlers: HashMap<Signal, SignalHandler> = HashMap::new();
+/// handlers.insert(Signal::SIGINT, Box::new(|| true));
+/// handlers.insert(
+///     Signal::SIGHUP,
+///     Box::new(|| {
+///         println!("Reloading the configuration");
+///         false
+///     }),
+/// );
+/// ctrlc2::set_handlers(handlers).expect("Error setting signal handlers");
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::NoSignals`] if `handlers` is empty, or an error if a system error
+/// occurred while setting the handler.
+pub fn set_handlers(mut handlers: HashMap<Signal, SignalHandler>) -> Result<JoinHandle<()>, Error> {
+    let signals: Vec<Signal> = handlers.keys().copied().collect();
+    set_handler_for(&signals, move |signal| {
+        handlers
+            .get_mut(&signal)
+            .map_or(false, |user_handler| user_handler())
     })
 }
+
+/// Register signal handler for Ctrl-C that exits the process on the second signal.
+///
+/// The first signal invokes `graceful`, which should start a graceful shutdown. Any
+/// subsequent signal bypasses `graceful` and calls
+/// [`std::process::exit(exit_code)`](std::process::exit) directly from the signal handling
+/// thread, for users that don't want to wait for the graceful shutdown to finish.
+///
+/// This is the same as [`set_handler_with_force_exit_after()`](fn.set_handler_with_force_exit_after.html)
+/// with a threshold of two. To give the graceful shutdown a bounded window even on the second
+/// signal, use [`set_handler_with_force_exit_delay()`](fn.set_handler_with_force_exit_delay.html).
+///
+/// # Example
+/// ```no_run
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// let running = Arc::new(AtomicBool::new(true));
+/// let r = running.clone();
+///
+/// ctrlc2::set_handler_with_force_exit(move || r.store(false, Ordering::SeqCst), 130)
+///     .expect("Error setting Ctrl-C handler");
+///
+/// while running.load(Ordering::SeqCst) {}
+/// println!("Shutting down gracefully, press Ctrl-C again to force quit...");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_force_exit<F>(graceful: F, exit_code: i32) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() + 'static + Send,
+{
+    set_handler_with_force_exit_after(graceful, exit_code, 2)
+}
+
+/// Register signal handler for Ctrl-C that runs `cleanup` and then exits the process.
+///
+/// The first signal runs `cleanup` on the signal handling thread, e.g. to flush buffers, and
+/// then calls [`std::process::exit(exit_code)`](std::process::exit). The process exits even if
+/// `cleanup` panics. Unlike with
+/// [`set_handler_with_force_exit()`](fn.set_handler_with_force_exit.html), which exits on the
+/// second signal, the first signal already ends the process. By convention, processes
+/// terminated by Ctrl-C exit with `130`, i.e. `128 + SIGINT`; see also
+/// [`re_raise()`](fn.re_raise.html). To exit with an exit code depending on the signal, use
+/// [`set_exit_handler_with()`](fn.set_exit_handler_with.html).
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_exit_handler(130, || println!("Flushing to disk..."))
+///     .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_exit_handler<F>(exit_code: i32, cleanup: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnOnce() + 'static + Send,
+{
+    set_exit_handler_with(move |_| exit_code, cleanup)
+}
+
+/// Register signal handler for Ctrl-C that runs `cleanup` and then exits the process with an
+/// exit code computed from the received signal.
+///
+/// Works like [`set_exit_handler()`](fn.set_exit_handler.html), but the process exits with
+/// `exit_code(signal)`, which is called after `cleanup`. Pass [`exit_code()`](fn.exit_code.html)
+/// for the conventional exit status of a process terminated by the signal, e.g. `130` for
+/// `SIGINT`, `143` for `SIGTERM` and `129` for `SIGHUP`.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_exit_handler_with(ctrlc2::exit_code, || println!("Flushing to disk..."))
+///     .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_exit_handler_with<C, F>(mut exit_code: C, cleanup: F) -> Result<JoinHandle<()>, Error>
+where
+    C: FnMut(Signal) -> i32 + 'static + Send,
+    F: FnOnce() + 'static + Send,
+{
+    let mut cleanup = Some(cleanup);
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |signal| {
+            if let Some(cleanup) = cleanup.take() {
+                // The panic is reported by the panic hook.
+                let _ = panic::catch_unwind(AssertUnwindSafe(cleanup));
+            }
+            std::process::exit(exit_code(signal))
+        },
+        Default::default(),
+    )
+}
+
+/// Returns the conventional exit status of a process terminated by `signal`.
+///
+/// On Unix, this is `128 + signum`, the exit status shells report for processes killed by a
+/// signal, e.g. `130` for `SIGINT`. On Windows, this is `STATUS_CONTROL_C_EXIT`, the exit code
+/// of the default handler routine for every event.
+///
+/// # Example
+/// ```
+/// # #[cfg(unix)]
+/// assert_eq!(ctrlc2::exit_code(ctrlc2::Signal::SIGTERM), 143);
+/// ```
+pub fn exit_code(signal: Signal) -> i32 {
+    platform::exit_code(signal)
+}
+
+/// Register signal handler for Ctrl-C that exits the process once `threshold` signals have
+/// been received.
+///
+/// Every signal before the `threshold`th one invokes `graceful`. The `threshold`th signal
+/// bypasses `graceful` and calls [`std::process::exit(exit_code)`](std::process::exit)
+/// directly from the signal handling thread. A `threshold` of zero or one exits on the first
+/// signal.
+///
+/// # Warning
+/// `graceful` runs on the signal handling thread, and signals are handled one at a time.
+/// A forced exit can therefore only happen after `graceful` has returned, so `graceful` should
+/// only initiate the shutdown instead of performing it. If signals arrive nearly
+/// simultaneously, the forced exit happens right after the first `graceful` invocation,
+/// possibly before the graceful shutdown even started.
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_force_exit_after<F>(
+    mut graceful: F,
+    exit_code: i32,
+    threshold: usize,
+) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() + 'static + Send,
+{
+    let mut count = 0usize;
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            count += 1;
+            if count >= threshold {
+                std::process::exit(exit_code);
+            }
+            graceful();
+            false
+        },
+        Default::default(),
+    )
+}
+
+/// Handle to tell a handler set by
+/// [`set_handler_with_force_exit_delay()`](fn.set_handler_with_force_exit_delay.html) that the
+/// graceful shutdown is complete, which cancels the pending force-exit.
+#[derive(Debug, Clone)]
+pub struct CleanupHandle {
+    state: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CleanupHandle {
+    /// Mark the graceful shutdown as complete. A pending force-exit is cancelled right away,
+    /// and later signals no longer exit the process.
+    pub fn complete(&self) {
+        let (done, changed) = &*self.state;
+        *done.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        changed.notify_all();
+    }
+
+    /// Returns whether [`complete()`](#method.complete) was called.
+    pub fn is_complete(&self) -> bool {
+        *self.state.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Register signal handler for Ctrl-C that exits the process if the graceful shutdown doesn't
+/// complete within `grace` after the second signal.
+///
+/// Like with [`set_handler_with_force_exit()`](fn.set_handler_with_force_exit.html), the first
+/// signal invokes `graceful`. The second signal doesn't exit the process right away, which
+/// could lose in-flight data, but sets a deadline `grace` from then: the signal handling thread
+/// waits until then and calls [`std::process::exit(exit_code)`](std::process::exit) unless the
+/// returned [`CleanupHandle`] was told that the shutdown is complete in the meantime. Signals
+/// arriving while the deadline is pending don't shorten it. Once the shutdown is complete,
+/// further signals are ignored.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// let (_, cleanup) = ctrlc2::set_handler_with_force_exit_delay(
+///     move || tx.send(()).unwrap(),
+///     130,
+///     Duration::from_secs(2),
+/// )
+/// .expect("Error setting Ctrl-C handler");
+///
+/// rx.recv().unwrap();
+/// println!("Flushing to disk...");
+/// cleanup.complete();
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_force_exit_delay<F>(
+    mut graceful: F,
+    exit_code: i32,
+    grace: std::time::Duration,
+) -> Result<(JoinHandle<()>, CleanupHandle), Error>
+where
+    F: FnMut() + 'static + Send,
+{
+    let cleanup = CleanupHandle {
+        state: Arc::new((Mutex::new(false), Condvar::new())),
+    };
+    let state = Arc::clone(&cleanup.state);
+    let mut first = true;
+    let handle = init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            let (done, changed) = &*state;
+            if first {
+                first = false;
+                graceful();
+                return false;
+            }
+            let done = done.lock().unwrap_or_else(|e| e.into_inner());
+            let (done, _) = changed
+                .wait_timeout_while(done, grace, |done| !*done)
+                .unwrap_or_else(|e| e.into_inner());
+            if !*done {
+                std::process::exit(exit_code);
+            }
+            false
+        },
+        Default::default(),
+    )?;
+    Ok((handle, cleanup))
+}
+
+/// Register signal handler for Ctrl-C that runs only once.
+///
+/// On the first signal, the handler is unset as if by
+/// [`unset_handler()`](fn.unset_handler.html) and `user_handler` is run. The previous signal
+/// dispositions are restored before `user_handler` runs, so a second Ctrl-C terminates the
+/// process as usual, even while `user_handler` is still running. The returned `JoinHandle`
+/// resolves once `user_handler` has returned.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler_once(|| println!("Skipping intro"))
+///     .expect("Error setting Ctrl-C handler");
+/// // Play the intro...
+/// handle.join().unwrap();
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_once<F>(user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnOnce() + 'static + Send,
+{
+    let own_source = Arc::new(Mutex::new(Weak::<platform::Source>::new()));
+    let thread_source = Arc::clone(&own_source);
+    // Keep the signal handling thread waiting until it knows its own source.
+    let mut own = own_source.lock().unwrap();
+    let mut user_handler = Some(user_handler);
+    let (handle, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            let source = thread_source.lock().unwrap().clone();
+            let mut guard = lock_init();
+            if let Err(e) = unset_if_current(&mut guard, &source) {
+                eprintln!("Error unsetting Ctrl-C handler: {}", e);
+            }
+            drop(guard);
+
+            if let Some(user_handler) = user_handler.take() {
+                user_handler();
+            }
+            true
+        },
+        Default::default(),
+        &HandlerConfig::default(),
+    )?;
+    *own = Arc::downgrade(&source);
+    Ok(handle)
+}
+
+/// What to do after a handler set by
+/// [`set_handler_chained()`](fn.set_handler_chained.html) has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chain {
+    /// Keep handling signals.
+    Continue,
+    /// Invoke the handler that was registered before ours, then keep handling signals.
+    Propagate,
+    /// Stop handling signals, like returning `true` from the handler of
+    /// [`set_handler()`](fn.set_handler.html).
+    Stop,
+}
+
+/// Register signal handler for Ctrl-C that can pass signals on to the previous handler.
+///
+/// Works like [`set_handler_with_signal()`](fn.set_handler_with_signal.html), but the handler
+/// decides after each signal whether the handler that was registered before ours should see
+/// it as well, see [`Chain`].
+///
+/// On Unix, propagating a signal temporarily re-installs the previous disposition and raises
+/// the signal again, so a previous `SIG_DFL` disposition terminates the process and `SIG_IGN`
+/// does nothing. Signals arriving in the meantime are handled by the previous disposition.
+///
+/// On Windows, our handler routine always returns `FALSE`, so the next registered routine is
+/// called by the OS right away, before our handler runs. [`Chain::Propagate`] is the same as
+/// [`Chain::Continue`] there.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_chained(|_| {
+///     println!("Cleaning up before the previous handler runs");
+///     ctrlc2::Chain::Propagate
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_chained<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(Signal) -> Chain + 'static + Send,
+{
+    let own_source = Arc::new(Mutex::new(Weak::<platform::Source>::new()));
+    let thread_source = Arc::clone(&own_source);
+    // Keep the signal handling thread waiting until it knows its own source.
+    let mut own = own_source.lock().unwrap();
+    let (handle, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |signal| match user_handler(signal) {
+            Chain::Continue => false,
+            Chain::Stop => true,
+            Chain::Propagate => {
+                let source = thread_source.lock().unwrap().upgrade();
+                match source {
+                    Some(source) => {
+                        if let Err(e) = unsafe { platform::chain(&source, signal) } {
+                            eprintln!(
+                                "Error passing on {:?} to the previous handler: {}",
+                                signal, e
+                            );
+                        }
+                    }
+                    None => eprintln!(
+                        "Error passing on {:?} to the previous handler: the handler was unset",
+                        signal
+                    ),
+                }
+                false
+            }
+        },
+        platform::Options {
+            chain: true,
+            ..Default::default()
+        },
+        &HandlerConfig::default(),
+    )?;
+    *own = Arc::downgrade(&source);
+    Ok(handle)
+}
+
+/// Signal dispositions that were in place before a handler was set by
+/// [`set_handler_saving_previous()`](fn.set_handler_saving_previous.html).
+///
+/// On Unix, this holds the `sigaction`s that were replaced. On Windows, handler routines are
+/// stacked instead of replaced, so there is nothing to hold besides the knowledge that ours
+/// was added.
+///
+/// Pass it to [`restore()`](fn.restore.html) to re-install the previous dispositions.
+#[derive(Debug)]
+pub struct PreviousDisposition {
+    disposition: platform::Disposition,
+    source: Weak<platform::Source>,
+}
+
+/// Register signal handler for Ctrl-C and return the signal dispositions it replaced.
+///
+/// Works like [`set_handler()`](fn.set_handler.html). The returned
+/// [`PreviousDisposition`] can later be passed to [`restore()`](fn.restore.html), which lets
+/// libraries and test harnesses use Ctrl-C handling temporarily without permanently
+/// clobbering the handler of their host application.
+///
+/// # Example
+/// ```no_run
+/// let (handle, previous) = ctrlc2::set_handler_saving_previous(|| true)
+///     .expect("Error setting Ctrl-C handler");
+/// // ...
+/// ctrlc2::restore(previous).expect("Error restoring previous Ctrl-C handler");
+/// handle.join().unwrap();
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_saving_previous<F>(
+    mut user_handler: F,
+) -> Result<(JoinHandle<()>, PreviousDisposition), Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    let (handle, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler(),
+        Default::default(),
+        &HandlerConfig::default(),
+    )?;
+    let previous = PreviousDisposition {
+        disposition: platform::previous_disposition(&source),
+        source: Arc::downgrade(&source),
+    };
+    Ok((handle, previous))
+}
+
+/// Re-install the signal dispositions saved by
+/// [`set_handler_saving_previous()`](fn.set_handler_saving_previous.html).
+///
+/// If the handler that saved them is still set, it is unset as if by
+/// [`unset_handler()`](fn.unset_handler.html). Otherwise, the saved dispositions are
+/// re-installed regardless of any handler set in the meantime.
+///
+/// # Errors
+/// Will return an error if a system error occurred while restoring the dispositions.
+pub fn restore(previous: PreviousDisposition) -> Result<(), Error> {
+    let mut guard = lock_init();
+
+    if !unset_if_current(&mut guard, &previous.source)? {
+        unsafe { platform::restore_disposition(&previous.disposition)? };
+    }
+
+    Ok(())
+}
+
+/// Register signal handler for Ctrl-C that is told the details about each received signal.
+///
+/// Works like [`set_handler_with_signal()`](fn.set_handler_with_signal.html), but the handler
+/// receives a [`SigInfo`], which tells who sent the signal. This allows e.g. honoring `SIGTERM`
+/// only if it was sent by a known supervisor process.
+///
+/// Only available on Unix.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_with_info(|info| {
+///     println!("Received {:?} from process {}", info.signal, info.pid);
+///     true
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+#[cfg(unix)]
+pub fn set_handler_with_info<F>(user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(SigInfo) -> bool + 'static + Send,
+{
+    init_and_register_for(
+        platform::DEFAULT_SIGNALS,
+        user_handler,
+        Default::default(),
+        &HandlerConfig::default(),
+    )
+    .map(|(handle, _)| handle)
+}
+
+/// Register signal handler for Ctrl-C that is told which console control event was received.
+///
+/// Works like [`set_handler_with_signal()`](fn.set_handler_with_signal.html), but the handler
+/// receives a [`WindowsEvent`], which tells Ctrl+C, Ctrl+Break, closing the console window and
+/// so on apart. Closing the console window and logging off or shutting down are only handled
+/// with the termination feature.
+///
+/// Only available on Windows.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_with_event(|event| {
+///     match event {
+///         ctrlc2::WindowsEvent::CtrlBreak => println!("Dumping state"),
+///         _ => println!("Cleaning up"),
+///     }
+///     event != ctrlc2::WindowsEvent::CtrlBreak
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+#[cfg(windows)]
+pub fn set_handler_with_event<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(WindowsEvent) -> bool + 'static + Send,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |signal| user_handler(WindowsEvent::from(signal)),
+        Default::default(),
+    )
+}
+
+/// Register a signal handler for Ctrl-C that forwards each received signal over a channel.
+///
+/// This is an alternative to [`set_handler()`](fn.set_handler.html) for programs that want
+/// to wait for Ctrl-C alongside other events in their own loop. The signal handling thread
+/// stops once the receiver has been dropped and the next signal arrives, or when the
+/// handler is unset with [`unset_handler()`](fn.unset_handler.html).
+///
+/// # Example
+/// ```no_run
+/// let (rx, handle) = ctrlc2::channel().expect("Error setting Ctrl-C handler");
+///
+/// println!("Waiting for Ctrl-C...");
+/// let signal = rx.recv().expect("Could not receive from channel.");
+/// println!("Got {:?}! Exiting...", signal);
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn channel() -> Result<(Receiver<Signal>, JoinHandle<()>), Error> {
+    let (tx, rx) = mpsc::channel();
+    let handle = set_handler_sender(tx)?;
+    Ok((rx, handle))
+}
+
+/// Register a signal handler for Ctrl-C that sets the returned flag.
+///
+/// The flag is `false` until the first signal is received, and `true` afterwards. The signal
+/// handling thread exits after setting it, so further signals are trapped but ignored. The flag
+/// is stored with [`Ordering::Release`](std::sync::atomic::Ordering::Release); loading it with
+/// [`Ordering::Acquire`](std::sync::atomic::Ordering::Acquire) makes everything the signal
+/// handling thread did before visible, although `Relaxed` is enough to just poll it.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::atomic::Ordering;
+///
+/// let (shutdown, _handle) = ctrlc2::shutdown_flag().expect("Error setting Ctrl-C handler");
+/// while !shutdown.load(Ordering::Acquire) {
+///     // Do some work...
+/// }
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn shutdown_flag() -> Result<(Arc<AtomicBool>, JoinHandle<()>), Error> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&flag);
+    let handle = set_handler(move || {
+        handler_flag.store(true, Ordering::Release);
+        true
+    })?;
+    Ok((flag, handle))
+}
+
+/// Block the calling thread until Ctrl-C is received and return the received signal.
+///
+/// Installs the os handler for the same signals as [`set_handler()`](fn.set_handler.html),
+/// waits on the calling thread instead of spawning a new one, and restores the previous signal
+/// dispositions before returning. The function can therefore be called again afterwards, and
+/// signals arriving between two calls get their previous disposition.
+///
+/// # Example
+/// ```no_run
+/// println!("Waiting for Ctrl-C...");
+/// let signal = ctrlc2::wait_for_signal().expect("Error waiting for Ctrl-C");
+/// println!("Got {:?}! Exiting...", signal);
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred. If the wait is ended by [`unset_handler()`](fn.unset_handler.html) from
+/// another thread, an [`Error::System`] of kind
+/// [`Interrupted`](std::io::ErrorKind::Interrupted) is returned.
+pub fn wait_for_signal() -> Result<Signal, Error> {
+    let source = register_source(platform::DEFAULT_SIGNALS, Default::default())?;
+
+    let signal = unsafe { platform::block_ctrl_c(&source) };
+    if let Ok(Some(signal)) = signal {
+        record_received(signal, 1);
+    }
+
+    let mut guard = lock_init();
+    if guard
+        .as_ref()
+        .map_or(false, |current| Arc::ptr_eq(current, &source))
+    {
+        guard.take();
+        INIT.store(false, Ordering::Release);
+        unsafe { platform::deinit_os_handler(&source)? };
+    }
+
+    match signal? {
+        Some(signal) => Ok(signal),
+        None => Err(Error::System(std::io::ErrorKind::Interrupted.into())),
+    }
+}
+
+/// Register signal handler for Ctrl-C, running it on a thread configured by `config`.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), which is the same as calling this with
+/// [`HandlerConfig::default()`].
+///
+/// # Example
+/// ```no_run
+/// let config = ctrlc2::HandlerConfig {
+///     name: Some("myapp-ctrl-c".into()),
+///     stack_size: Some(64 * 1024),
+///     ..Default::default()
+/// };
+/// ctrlc2::set_handler_with_config(config, || true).expect("Error setting Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler or spawning the
+/// signal handling thread. If spawning the thread fails, the previous signal dispositions are
+/// restored and no handler is set.
+pub fn set_handler_with_config<F>(
+    config: HandlerConfig,
+    mut user_handler: F,
+) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    init_and_register(
+        &config.signals(platform::DEFAULT_SIGNALS),
+        move |_| user_handler(),
+        config.options(),
+        &config,
+    )
+    .map(|(handle, _)| handle)
+}
+
+/// Register signal handler for Ctrl-C that reports fatal errors of the signal handling thread
+/// to `sink`.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but a system error while waiting for a
+/// signal is sent to `sink` before the signal handling thread exits, instead of being reported
+/// on stderr, like with [`HandlerConfig::on_error`]. The sender is dropped when the thread
+/// exits, so a supervisor can block on the receiver and restart the signal handling if it
+/// receives an error. Unset the handler before setting a new one in that case.
+///
+/// # Example
+/// ```no_run
+/// let (sink, errors) = std::sync::mpsc::channel();
+/// ctrlc2::set_handler_with_error_sink(sink, || true).expect("Error setting Ctrl-C handler");
+/// if let Ok(e) = errors.recv() {
+///     eprintln!("Ctrl-C handling failed: {}", e);
+///     ctrlc2::unset_handler().expect("Error unsetting Ctrl-C handler");
+/// }
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_with_error_sink<F>(
+    sink: mpsc::Sender<Error>,
+    user_handler: F,
+) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    // `Sender` only implements `Sync` in newer Rust versions.
+    let sink = Mutex::new(sink);
+    let config = HandlerConfig {
+        on_error: Some(Arc::new(move |e| {
+            let _ = sink.lock().unwrap_or_else(|e| e.into_inner()).send(e);
+        })),
+        ..Default::default()
+    };
+    set_handler_with_config(config, user_handler)
+}
+
+/// The same as ctrlc2::set_handler but errors if a handler already exists for the signal(s).
+///
+/// # Errors
+/// Will return an error if another handler exists or if a system error occurred while setting the
+/// handler.
+pub fn try_set_handler<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler(),
+        platform::Options {
+            overwrite: false,
+            ..Default::default()
+        },
+    )
+}
+
+/// Register signal handler for the given signals only, unless another handler exists for
+/// any of them.
+///
+/// Works like [`set_handler_for()`](fn.set_handler_for.html), but the current disposition of
+/// each of the given signals is checked first, and none of the signals is touched if one of
+/// them already has a handler. Unlike [`try_set_handler()`](fn.try_set_handler.html), handlers
+/// for other signals are of no concern.
+///
+/// On Windows, handler routines are stacked instead of replaced and cannot be queried, so no
+/// conflict is ever reported.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(unix)]
+/// match ctrlc2::try_set_handler_for(&[ctrlc2::Signal::SIGTERM], |_| true) {
+///     Ok(_) => println!("Handling SIGTERM"),
+///     Err(ctrlc2::Error::HandlerExists(signal)) => println!("{:?} is handled elsewhere", signal),
+///     Err(e) => panic!("Error setting signal handler: {}", e),
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::HandlerExists`] with the first conflicting signal,
+/// [`Error::MultipleHandlers`] if a handler is already set by this crate or another handler
+/// was registered concurrently, [`Error::NoSignals`] if `signals` is empty,
+/// [`Error::Unsupported`] if one of them can't be handled on this platform, or an error if a
+/// system error occurred while setting the handler.
+pub fn try_set_handler_for<F>(signals: &[Signal], user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    if signals.is_empty() {
+        return Err(Error::NoSignals);
+    }
+    if INIT.load(Ordering::Acquire) {
+        return Err(Error::MultipleHandlers);
+    }
+    if let Some(signal) = unsafe { platform::existing_handler(signals)? } {
+        return Err(Error::HandlerExists(signal));
+    }
+    init_and_set_handler(
+        signals,
+        user_handler,
+        platform::Options {
+            overwrite: false,
+            ..Default::default()
+        },
+    )
+}
+
+/// Returns [`Error::Unsupported`] with the first signal that can't be handled on this platform.
+fn check_supported(signals: &[Signal]) -> Result<(), Error> {
+    match signals
+        .iter()
+        .find(|&&signal| !platform::is_supported(signal))
+    {
+        Some(&signal) => Err(Error::Unsupported(signal)),
+        None => Ok(()),
+    }
+}
+
+/// Install the os handler and register it, without a signal handling thread servicing it.
+fn register_source(
+    signals: &[Signal],
+    options: platform::Options,
+) -> Result<Arc<platform::Source>, Error> {
+    check_supported(signals)?;
+    let mut guard = lock_init();
+    if guard.is_some() {
+        return Err(Error::MultipleHandlers);
+    }
+    let source = Arc::new(unsafe { platform::init_os_handler(signals, options)? });
+    *guard = Some(Arc::clone(&source));
+    INIT.store(true, Ordering::Release);
+    Ok(source)
+}
+
+fn init_and_set_handler<F>(
+    signals: &[Signal],
+    user_handler: F,
+    options: platform::Options,
+) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    init_and_register(signals, user_handler, options, &HandlerConfig::default())
+        .map(|(handle, _)| handle)
+}
+
+fn init_and_register<F>(
+    signals: &[Signal],
+    user_handler: F,
+    options: platform::Options,
+    config: &HandlerConfig,
+) -> Result<(JoinHandle<()>, Arc<platform::Source>), Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    init_and_register_for(signals, user_handler, options, config)
+}
+
+/// Like [`init_and_register()`], but generic over what the handler receives.
+fn init_and_register_for<R, F>(
+    signals: &[Signal],
+    user_handler: F,
+    options: platform::Options,
+    config: &HandlerConfig,
+) -> Result<(JoinHandle<()>, Arc<platform::Source>), Error>
+where
+    R: Received,
+    F: FnMut(R) -> bool + 'static + Send,
+{
+    init_and_register_with_exit(signals, user_handler, options, config, false, drop)
+}
+
+/// Like [`init_and_register_for()`], but the signal handling thread returns `exit` applied to
+/// the reason it stopped, and stops on a panic in the handler if `stop_on_panic` is set.
+fn init_and_register_with_exit<R, F, E, T>(
+    signals: &[Signal],
+    user_handler: F,
+    options: platform::Options,
+    config: &HandlerConfig,
+    stop_on_panic: bool,
+    exit: E,
+) -> Result<(JoinHandle<T>, Arc<platform::Source>), Error>
+where
+    R: Received,
+    F: FnMut(R) -> bool + 'static + Send,
+    E: FnOnce(HandlerExit) -> T + 'static + Send,
+    T: Send + 'static,
+{
+    check_supported(signals)?;
+    if !INIT.load(Ordering::Acquire) {
+        let mut guard = lock_init();
+
+        if guard.is_none() {
+            let (handle, source) =
+                set_handler_inner(signals, user_handler, options, config, stop_on_panic, exit)?;
+            *guard = Some(Arc::clone(&source));
+            INIT.store(true, Ordering::Release);
+            return Ok((handle, source));
+        }
+    }
+
+    Err(Error::MultipleHandlers)
+}
+
+/// What the signal handling thread passes to the handler for each received signal.
+trait Received: Copy + Send + 'static {
+    /// Block until a signal is received, see `platform::block_ctrl_c()`.
+    unsafe fn block(source: &platform::Source) -> Result<Option<Self>, Error>;
+
+    /// The received signal.
+    fn signal(&self) -> Signal;
+
+    /// Add the signals received in the meantime, called after waiting while paused.
+    unsafe fn collect(&mut self, _source: &platform::Source) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The number of signals received.
+    fn count(&self) -> u64 {
+        1
+    }
+
+    /// Whether the handler was unset after the signals were received.
+    fn is_last(&self) -> bool {
+        false
+    }
+}
+
+impl Received for Signal {
+    unsafe fn block(source: &platform::Source) -> Result<Option<Self>, Error> {
+        // The platform error type differs between Unix and Windows.
+        #[allow(clippy::needless_question_mark)]
+        Ok(platform::block_ctrl_c(source)?)
+    }
+
+    fn signal(&self) -> Signal {
+        *self
+    }
+}
+
+#[cfg(unix)]
+impl Received for SigInfo {
+    unsafe fn block(source: &platform::Source) -> Result<Option<Self>, Error> {
+        platform::block_ctrl_c_info(source)
+    }
+
+    fn signal(&self) -> Signal {
+        self.signal
+    }
+}
+
+/// Signals received since the handler last ran, see
+/// [`set_handler_with_count()`](fn.set_handler_with_count.html).
+#[derive(Clone, Copy)]
+struct Batch {
+    signal: Signal,
+    count: u32,
+    is_last: bool,
+}
+
+impl Received for Batch {
+    unsafe fn block(source: &platform::Source) -> Result<Option<Self>, Error> {
+        Ok(Signal::block(source)?.map(|signal| Batch {
+            signal,
+            count: 1,
+            is_last: false,
+        }))
+    }
+
+    unsafe fn collect(&mut self, source: &platform::Source) -> Result<(), Error> {
+        while platform::is_pending(source)? {
+            match Signal::block(source)? {
+                Some(signal) => {
+                    self.signal = signal;
+                    self.count = self.count.saturating_add(1);
+                }
+                None => {
+                    self.is_last = true;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn signal(&self) -> Signal {
+        self.signal
+    }
+
+    fn count(&self) -> u64 {
+        self.count.into()
+    }
+
+    fn is_last(&self) -> bool {
+        self.is_last
+    }
+}
+
+fn set_handler_inner<R, F, E, T>(
+    signals: &[Signal],
+    mut user_handler: F,
+    options: platform::Options,
+    config: &HandlerConfig,
+    stop_on_panic: bool,
+    exit: E,
+) -> Result<(JoinHandle<T>, Arc<platform::Source>), Error>
+where
+    R: Received,
+    F: FnMut(R) -> bool + 'static + Send,
+    E: FnOnce(HandlerExit) -> T + 'static + Send,
+    T: Send + 'static,
+{
+    let source = Arc::new(unsafe { platform::init_os_handler(signals, options)? });
+
+    let thread_source = Arc::clone(&source);
+    let thread_config = config.clone();
+    let mut replaced: Option<Box<dyn FnMut() -> bool + Send>> = None;
+    let mut last_run = None;
+    let thread_signals = signals.to_vec();
+    let builder = config.thread_builder().spawn(move || {
+        // Receive the signals here even if they are blocked on the other threads.
+        if let Err(e) = platform::unblock_on_current_thread(&thread_signals) {
+            thread_config.report_error(e.into());
+        }
+        let reason = loop {
+            let mut received = match unsafe { R::block(&thread_source) } {
+                Ok(Some(received)) => received,
+                Ok(None) => break HandlerExit::Unregistered,
+                Err(e) if e.is_interrupted() => continue,
+                Err(e) => {
+                    thread_config.report_error(e);
+                    break HandlerExit::Failed;
+                }
+            };
+            if !wait_while_paused(&thread_source) {
+                break HandlerExit::Unregistered;
+            }
+            if let Err(e) = unsafe { received.collect(&thread_source) } {
+                thread_config.report_error(e);
+                break HandlerExit::Failed;
+            }
+            // Signals read from the source after the handler was unset are discarded with it.
+            // Checked under the lock, so they are not counted after the reset either.
+            {
+                let guard = lock_init();
+                if !guard
+                    .as_ref()
+                    .map_or(false, |c| Arc::ptr_eq(c, &thread_source))
+                {
+                    drop(guard);
+                    unsafe { platform::signal_handled(&thread_source, received.signal()) };
+                    break HandlerExit::Unregistered;
+                }
+                record_received(received.signal(), received.count());
+            }
+            thread_config.forward(received.signal());
+            trace!(
+                info,
+                signal = ?received.signal(),
+                count = received.count(),
+                total = SIGNALS_RECEIVED.load(Ordering::Relaxed),
+                "Ctrl-C signal received"
+            );
+            #[cfg(feature = "metrics")]
+            metrics::counter!(
+                "ctrlc2.signals_total",
+                "signal" => platform::signal_name(received.signal())
+            )
+            .increment(received.count());
+            if thread_config.debounced(last_run) {
+                trace!(debug, signal = ?received.signal(), "Ctrl-C signal debounced");
+                unsafe { platform::signal_handled(&thread_source, received.signal()) };
+                continue;
+            }
+            if let Some(replacement) = take_replacement(&thread_source) {
+                replaced = Some(replacement);
+            }
+            #[cfg(feature = "metrics")]
+            let started = std::time::Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| match &mut replaced {
+                Some(replaced) => replaced(),
+                None => user_handler(received),
+            }));
+            #[cfg(feature = "metrics")]
+            metrics::histogram!("ctrlc2.handler_duration_seconds")
+                .record(started.elapsed().as_secs_f64());
+            last_run = Some(std::time::Instant::now());
+            unsafe { platform::signal_handled(&thread_source, received.signal()) };
+            match result {
+                Ok(true) => break HandlerExit::Completed,
+                Ok(false) => {}
+                Err(_) if stop_on_panic => break HandlerExit::Panicked,
+                Err(payload) => match thread_config.report_panic(payload) {
+                    PanicPolicy::Continue => {}
+                    PanicPolicy::Stop => break HandlerExit::Panicked,
+                    PanicPolicy::Exit(code) => std::process::exit(code),
+                },
+            }
+            if received.is_last() {
+                break HandlerExit::Completed;
+            }
+        };
+        trace!(info, ?reason, "Ctrl-C handling thread exiting");
+        // Don't keep join_timeout() waiting past its deadline for the handler to be dropped, or
+        // for `exit`, which may wait for other threads.
+        drop(user_handler);
+        drop(replaced);
+        let exit = exit(reason);
+        join::announce_exit();
+        exit
+    });
+
+    match builder {
+        Ok(handle) => {
+            // Only now, so a handler that couldn't be set leaves the dispositions alone.
+            if let Err(e) = config.apply_dispositions() {
+                let _ = unsafe { platform::deinit_os_handler(&source) };
+                return Err(e);
+            }
+            trace!(info, ?signals, "Ctrl-C handler installed");
+            *lock_replacement() = Some(Replacement {
+                source: Arc::downgrade(&source),
+                user_handler: None,
+            });
+            *HANDLER_THREAD.lock().unwrap_or_else(|e| e.into_inner()) =
+                Some((Arc::downgrade(&source), ThreadHandle::new(&handle)));
+            Ok((handle, source))
+        }
+        Err(e) => {
+            // Don't leave an os handler behind without a thread servicing it.
+            let _ = unsafe { platform::deinit_os_handler(&source) };
+            Err(Error::System(e))
+        }
+    }
+}
+
+/// Ignore the given signals, without a handler.
+///
+/// On Unix, the disposition of the signals is set to `SIG_IGN`, which, unlike a handler, is
+/// inherited by child processes created via `execve(2)`. This lets e.g. a wrapper of a
+/// subprocess survive the `SIGINT` the terminal sends to the whole process group. On Windows, a
+/// handler routine that claims the events and does nothing is registered.
+///
+/// Don't ignore signals handled by a handler set with this crate, which stops seeing them on
+/// Unix. Use [`reset()`](fn.reset.html) to undo this.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(unix)]
+/// ctrlc2::ignore(&[ctrlc2::Signal::SIGINT]).expect("Error ignoring SIGINT");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+pub fn ignore(signals: &[Signal]) -> Result<(), Error> {
+    unsafe { platform::ignore(signals)? };
+    Ok(())
+}
+
+/// Stop ignoring the given signals, see [`ignore()`](fn.ignore.html).
+///
+/// On Unix, the disposition of the signals is set to `SIG_DFL`, whether they were ignored or
+/// not. On Windows, the events are no longer claimed by the routine registered by `ignore()`.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+pub fn reset(signals: &[Signal]) -> Result<(), Error> {
+    unsafe { platform::reset(signals)? };
+    Ok(())
+}
+
+/// Current disposition of a signal, as returned by
+/// [`current_disposition()`](fn.current_disposition.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// The default action of the signal, `SIG_DFL`.
+    Default,
+    /// The signal is ignored, `SIG_IGN`.
+    Ignored,
+    /// The signal handler of this crate.
+    Own,
+    /// A signal handler installed by someone else.
+    Custom,
+}
+
+/// Returns the current disposition of `signal`, e.g. to check whether something replaced the
+/// handler of this crate after it was set.
+///
+/// On Unix, the disposition is read with `sigaction(2)`, without changing it. With
+/// `HandlerConfig::signal_hook` set, the signal handler is the one of `signal-hook`, which is
+/// reported as [`Disposition::Custom`].
+///
+/// On Windows, the registered handler routines can't be queried, so this is best-effort:
+/// [`Disposition::Own`] if the handler routine of this crate handles the event,
+/// [`Disposition::Ignored`] if it is ignored with [`ignore()`](fn.ignore.html), and
+/// [`Disposition::Default`] otherwise, even if other routines were registered. Routines
+/// registered after ours can keep it from seeing the event anyway, see
+/// `windows_handler_reachable()`.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(unix)]
+/// # {
+/// ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// // ...
+/// if ctrlc2::current_disposition(ctrlc2::Signal::SIGINT).unwrap() != ctrlc2::Disposition::Own {
+///     eprintln!("Something replaced the Ctrl-C handler");
+/// }
+/// # }
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred, e.g. of kind `EINVAL` for an invalid
+/// signal number.
+pub fn current_disposition(signal: Signal) -> Result<Disposition, Error> {
+    Ok(unsafe { platform::current_disposition(signal)? })
+}
+
+/// Deliver `signal` to the handler that is set, as if it had been received from the os.
+///
+/// The signal is queued for the signal handling thread, or for a
+/// [`RawSignalSource`](struct.RawSignalSource.html), directly: on Unix it is written to the
+/// self-pipe the signal handler writes to, on Windows it is added to the queue of received
+/// console control events. No signal is raised, so other handlers in the process don't see it,
+/// and tests can exercise shutdown logic deterministically on any platform. On Unix, the
+/// signal looks like it was sent by the process itself.
+///
+/// This is only meant for tests and only available with the `testing` feature. Don't enable the
+/// feature outside of `[dev-dependencies]`.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler(|| true).unwrap();
+/// # #[cfg(unix)]
+/// ctrlc2::simulate_signal(ctrlc2::Signal::SIGINT).unwrap();
+/// handle.join().unwrap();
+/// ```
+///
+/// # Errors
+/// Will return [`Error::NoHandler`] if no handler is set, or an error if a system error
+/// occurred.
+#[cfg(feature = "testing")]
+pub fn simulate_signal(signal: Signal) -> Result<(), Error> {
+    let guard = lock_init();
+    match guard.as_ref() {
+        Some(source) => {
+            unsafe { platform::simulate(source, signal)? };
+            Ok(())
+        }
+        None => Err(Error::NoHandler),
+    }
+}
+
+/// Deliver Ctrl-C to the current process through the os, e.g. to test shutdown logic.
+///
+/// Unlike [`simulate_signal()`](fn.simulate_signal.html), which hands the signal to the handler
+/// directly, this exercises the whole chain from the os to the handler, and every other handler
+/// of the process sees the signal as well. On Unix, `SIGINT` is raised on the calling thread
+/// with `raise(3)`, and has been handled by the os handler when this returns. On Windows,
+/// `CTRL_C_EVENT` is generated with `GenerateConsoleCtrlEvent()`, which requires the process to
+/// be attached to a console and reaches every process attached to it. The handler routine runs
+/// asynchronously there.
+///
+/// Without a handler set, this terminates the process, like a real Ctrl-C does.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// ctrlc2::raise_ctrl_c().expect("Error raising Ctrl-C");
+/// handle.join().unwrap();
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred, e.g. on Windows if the process has no
+/// console.
+pub fn raise_ctrl_c() -> Result<(), Error> {
+    platform::raise_ctrl_c()?;
+    Ok(())
+}
+
+/// Terminate the process as if `signal` had not been handled.
+///
+/// Exiting with [`std::process::exit()`] after cleaning up hides that the process was
+/// interrupted from shells and supervisors, which expect signal-based exit statuses. Call
+/// this at the end of the cleanup instead. On Unix, the disposition of `signal` is reset to
+/// `SIG_DFL` and the signal is raised, so the parent sees the process killed by the signal, or
+/// the `128 + signum` exit status in a shell. On Windows, the process exits with
+/// `STATUS_CONTROL_C_EXIT`, like the default handler routine does.
+///
+/// # Example
+/// ```no_run
+/// let signal = ctrlc2::wait_for_signal().expect("Error waiting for Ctrl-C");
+/// println!("Cleaning up");
+/// ctrlc2::re_raise(signal);
+/// ```
+pub fn re_raise(signal: Signal) -> ! {
+    platform::re_raise(signal)
+}
+
+/// Replace the handler of the signal handling thread.
+///
+/// The currently set handler keeps its os handler and its signal handling thread, which runs
+/// `user_handler` instead of the previous handler from now on, with the same meaning of the
+/// returned `bool` as for [`set_handler()`](fn.set_handler.html). Unlike unsetting the handler
+/// and setting a new one, signals are trapped all the time.
+///
+/// The replacement applies to every signal whose handling starts after this returns, including
+/// signals received earlier but not yet handled. A handler invocation already in progress is
+/// not waited for and completes with the previous handler. Replacing a handler that has not
+/// run a replacement yet drops the unused one.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler(|| {
+///     println!("Starting up, please wait");
+///     false
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// // Startup finished...
+/// ctrlc2::replace_handler(|| {
+///     println!("Shutting down");
+///     true
+/// })
+/// .expect("Error replacing Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return [`Error::NoHandler`] if no handler with a signal handling thread is set, e.g. if
+/// no handler is set at all or signals are received with [`register_raw()`](fn.register_raw.html).
+pub fn replace_handler<F>(user_handler: F) -> Result<(), Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    let guard = lock_init();
+    let current = guard.as_ref().ok_or(Error::NoHandler)?;
+    let mut replacement = lock_replacement();
+    match replacement.as_mut() {
+        Some(replacement) if is_source(&replacement.source, current) => {
+            replacement.user_handler = Some(Box::new(user_handler));
+            Ok(())
+        }
+        _ => Err(Error::NoHandler),
+    }
+}
+
+/// Handler that can be shared and swapped at runtime, see
+/// [`set_shared_handler()`](fn.set_shared_handler.html).
+pub type SharedHandler = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// Register a shared signal handler for Ctrl-C that can be swapped at runtime.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but the handler is reference counted
+/// instead of moved to the signal handling thread, so the same handler can be kept elsewhere,
+/// e.g. installed in several places of a framework, and the current one can be inspected with
+/// [`shared_handler()`](fn.shared_handler.html). It can be swapped atomically with
+/// [`swap_shared_handler()`](fn.swap_shared_handler.html) without reinstalling the os handler.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// ctrlc2::set_shared_handler(Arc::new(|| true)).expect("Error setting Ctrl-C handler");
+/// let previous = ctrlc2::swap_shared_handler(Arc::new(|| {
+///     println!("Plugin reloaded");
+///     true
+/// }))
+/// .expect("Error swapping Ctrl-C handler");
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_shared_handler(user_handler: SharedHandler) -> Result<JoinHandle<()>, Error> {
+    let cell = Arc::new(Mutex::new(user_handler));
+    let thread_cell = Arc::clone(&cell);
+    let (handle, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |_| {
+            // Don't keep the handler locked while it runs, so it can be swapped meanwhile.
+            let user_handler = Arc::clone(&thread_cell.lock().unwrap_or_else(|e| e.into_inner()));
+            user_handler()
+        },
+        Default::default(),
+        &HandlerConfig::default(),
+    )?;
+    *lock_shared() = Some(Shared {
+        source: Arc::downgrade(&source),
+        cell,
+    });
+    Ok(handle)
+}
+
+/// Replace the handler set with [`set_shared_handler()`](fn.set_shared_handler.html) and
+/// return the previous one.
+///
+/// The new handler applies to every signal whose handling starts after this returns. A handler
+/// invocation already in progress completes with the previous handler.
+///
+/// # Errors
+/// Will return [`Error::NoHandler`] if no shared handler is set.
+pub fn swap_shared_handler(user_handler: SharedHandler) -> Result<SharedHandler, Error> {
+    let guard = lock_init();
+    let current = guard.as_ref().ok_or(Error::NoHandler)?;
+    match lock_shared().as_ref() {
+        Some(shared) if is_source(&shared.source, current) => {
+            let mut cell = shared.cell.lock().unwrap_or_else(|e| e.into_inner());
+            Ok(std::mem::replace(&mut *cell, user_handler))
+        }
+        _ => Err(Error::NoHandler),
+    }
+}
+
+/// Returns the handler set with [`set_shared_handler()`](fn.set_shared_handler.html), or `None`
+/// if no shared handler is set.
+pub fn shared_handler() -> Option<SharedHandler> {
+    let guard = lock_init();
+    let current = guard.as_ref()?;
+    match lock_shared().as_ref() {
+        Some(shared) if is_source(&shared.source, current) => Some(Arc::clone(
+            &shared.cell.lock().unwrap_or_else(|e| e.into_inner()),
+        )),
+        _ => None,
+    }
+}
+
+fn lock_shared() -> MutexGuard<'static, Option<Shared>> {
+    SHARED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn lock_replacement() -> MutexGuard<'static, Option<Replacement>> {
+    REPLACEMENT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Take the handler passed to [`replace_handler()`] for the signal handling thread of `source`.
+fn take_replacement(source: &Arc<platform::Source>) -> Option<Box<dyn FnMut() -> bool + Send>> {
+    let mut replacement = lock_replacement();
+    match replacement.as_mut() {
+        Some(replacement) if is_source(&replacement.source, source) => {
+            replacement.user_handler.take()
+        }
+        _ => None,
+    }
+}
+
+fn is_source(weak: &Weak<platform::Source>, source: &Arc<platform::Source>) -> bool {
+    weak.upgrade()
+        .map_or(false, |weak| Arc::ptr_eq(&weak, source))
+}
+
+/// Returns the signals the currently set handler is installed for, or `None` if no handler is
+/// set.
+///
+/// These are the signals passed when setting the handler, except for those skipped because of
+/// [`HandlerConfig::skip_if_no_tty`].
+///
+/// # Example
+/// ```no_run
+/// let config = ctrlc2::HandlerConfig {
+///     skip_if_no_tty: true,
+///     ..Default::default()
+/// };
+/// ctrlc2::set_handler_with_config(config, || true).expect("Error setting Ctrl-C handler");
+/// if ctrlc2::installed_signals().map_or(true, |signals| signals.is_empty()) {
+///     println!("No terminal, not handling Ctrl-C");
+/// }
+/// ```
+pub fn installed_signals() -> Option<Vec<Signal>> {
+    lock_init().as_ref().map(|source| platform::signals(source))
+}
+
+/// Identifies the signal handling thread, see [`handler_thread_id()`](fn.handler_thread_id.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadHandle {
+    id: thread::ThreadId,
+    #[cfg(unix)]
+    pthread: std::os::unix::thread::RawPthread,
+}
+
+// A `pthread_t` only identifies the thread, like a `ThreadId` does.
+unsafe impl Send for ThreadHandle {}
+unsafe impl Sync for ThreadHandle {}
+
+impl ThreadHandle {
+    fn new<T>(handle: &JoinHandle<T>) -> Self {
+        #[cfg(unix)]
+        use std::os::unix::thread::JoinHandleExt;
+
+        ThreadHandle {
+            id: handle.thread().id(),
+            #[cfg(unix)]
+            pthread: handle.as_pthread_t(),
+        }
+    }
+
+    /// Returns the id of the signal handling thread.
+    pub fn id(&self) -> thread::ThreadId {
+        self.id
+    }
+
+    /// Returns the `pthread_t` of the signal handling thread, e.g. to send it a signal with
+    /// `pthread_kill(3)`.
+    ///
+    /// Only available on Unix.
+    #[cfg(unix)]
+    pub fn as_pthread_t(&self) -> std::os::unix::thread::RawPthread {
+        self.pthread
+    }
+}
+
+/// Returns the signal handling thread of the currently set handler, or `None` if no handler is
+/// set.
+///
+/// Useful for diagnostics, and on Unix to direct a signal at the signal handling thread. Also
+/// `None` for handlers without a signal handling thread of this crate, like the ones set with
+/// [`register_raw()`](fn.register_raw.html) or
+/// [`set_handler_inline()`](fn.set_handler_inline.html).
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// println!("Signals are handled on {:?}", ctrlc2::handler_thread_id().unwrap().id());
+/// ```
+pub fn handler_thread_id() -> Option<ThreadHandle> {
+    let guard = lock_init();
+    let current = guard.as_ref()?;
+    match &*HANDLER_THREAD.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some((source, thread)) if source.as_ptr() == Arc::as_ptr(current) => Some(*thread),
+        _ => None,
+    }
+}
+
+/// Whether Ctrl-C is likely to reach the handler, see
+/// [`handler_reachability_check()`](fn.handler_reachability_check.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReachabilityStatus {
+    /// No debugger is attached, Ctrl-C reaches the process as usual.
+    Reachable,
+    /// A debugger is attached, which may intercept Ctrl-C before it reaches the process.
+    DebuggerAttached,
+    /// Whether a debugger is attached can't be detected on this platform.
+    Unknown,
+}
+
+/// Detect whether Ctrl-C is likely to be intercepted before it reaches the handler.
+///
+/// Debuggers like gdb, lldb or WinDbg usually stop the process on Ctrl-C themselves instead
+/// of passing it on, so the handler seemingly never runs. This is a best-effort check for an
+/// attached debugger, so the program can print a hint about it. On Linux and Android it reads
+/// the `TracerPid` from `/proc/self/status`, on Windows it uses `IsDebuggerPresent()`. Other
+/// platforms report [`ReachabilityStatus::Unknown`].
+///
+/// # Example
+/// ```no_run
+/// if ctrlc2::handler_reachability_check() == ctrlc2::ReachabilityStatus::DebuggerAttached {
+///     eprintln!("Running under a debugger; Ctrl-C may be intercepted.");
+/// }
+/// ```
+pub fn handler_reachability_check() -> ReachabilityStatus {
+    match platform::debugger_attached() {
+        Some(true) => ReachabilityStatus::DebuggerAttached,
+        Some(false) => ReachabilityStatus::Reachable,
+        None => ReachabilityStatus::Unknown,
+    }
+}
+
+/// Returns whether the handler routine of the signal handler is reached by console control
+/// events, as far as that can be told.
+///
+/// Windows calls the registered handler routines last registered first, until one of them
+/// returns `TRUE`. A routine that was registered after ours, e.g. by another library, and
+/// consumes an event keeps ours from ever seeing it, so the handler doesn't run. The registered
+/// routines can't be enumerated, so this is best-effort:
+///
+/// - `Some(true)` if the handler routine has received one of the events it handles since the
+///   handler was set, so it was reachable at that time.
+/// - `Some(false)` if no handler is set, so there is no routine to reach.
+/// - `None` if no event has been received yet, so nothing is known.
+///
+/// To find out at startup, set the handler and send an event to the process group with
+/// `GenerateConsoleCtrlEvent()`, see [`raise_ctrl_c()`](fn.raise_ctrl_c.html). See
+/// [`HandlerConfig::propagate_events`] to let the routines registered before ours see the
+/// events as well.
+///
+/// Only available on Windows.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler(|| false).expect("Error setting Ctrl-C handler");
+/// // ...
+/// if ctrlc2::windows_handler_reachable() == Some(true) {
+///     println!("Ctrl-C reached our handler");
+/// }
+/// ```
+#[cfg(windows)]
+pub fn windows_handler_reachable() -> Option<bool> {
+    if !is_handler_set() {
+        return Some(false);
+    }
+    if platform::routine_reached() {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Returns whether a signal handler is currently set.
+///
+/// This lets independent parts of a program skip setting a handler if another part already
+/// did, without attempting the registration and handling [`Error::MultipleHandlers`].
+///
+/// The result is advisory only: if another thread sets or unsets a handler concurrently, it
+/// may be out of date by the time it is returned.
+///
+/// # Example
+/// ```no_run
+/// if !ctrlc2::is_handler_set() {
+///     ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// }
+/// ```
+pub fn is_handler_set() -> bool {
+    INIT.load(Ordering::Acquire)
+}
+
+/// Returns the number of signals received since the program started, or since the handler was
+/// last unset with [`unset_handler()`](fn.unset_handler.html).
+///
+/// The counter is incremented on the signal handling thread before the handler runs. Signals
+/// waited for with [`wait_for_signal()`](fn.wait_for_signal.html) are counted as well.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler(|| false).expect("Error setting Ctrl-C handler");
+/// // ...
+/// println!("Received {} interrupts", ctrlc2::signals_received());
+/// ```
+pub fn signals_received() -> u64 {
+    SIGNALS_RECEIVED.load(Ordering::Relaxed)
+}
+
+/// Returns whether signals of the same kind can be merged into one before the handler runs, so
+/// [`signals_received()`](fn.signals_received.html) and the number of handler invocations can
+/// be lower than the number of signals sent.
+///
+/// This reports the semantics of the handlers set by [`set_handler()`](fn.set_handler.html) and
+/// the other functions built on the os handler of this crate:
+///
+/// - On Unix, `true`. The kernel keeps at most one instance of each standard signal pending, so
+///   signals sent in quick succession, e.g. while the process is not scheduled, can arrive as
+///   one. The same holds for `register_signalfd()`, `set_handler_sigwait()` and
+///   `register_kqueue()`, while real-time signals handled by `set_handler_realtime()` are
+///   queued and never merged.
+/// - On Windows, `false`. The handler routine is called once for every console control event,
+///   and each call is queued for the signal handling thread.
+///
+/// # Example
+/// ```
+/// let exact = !ctrlc2::coalesces_signals();
+/// println!("Signal counts are {}", if exact { "exact" } else { "a lower bound" });
+/// ```
+pub const fn coalesces_signals() -> bool {
+    platform::COALESCES_SIGNALS
+}
+
+/// Returns the last received signal, or `None` if no signal has been received yet.
+///
+/// This tells which signal fired even if the handler isn't told, e.g. to log it after the loop
+/// watching a flag set by the handler ended. Unlike [`signals_received()`], it is not reset
+/// when the handler is unset.
+///
+/// # Example
+/// ```no_run
+/// let (shutdown, _handle) = ctrlc2::shutdown_flag().expect("Error setting Ctrl-C handler");
+/// while !shutdown.load(std::sync::atomic::Ordering::Acquire) {
+///     // Do some work...
+/// }
+/// println!("Exited due to {:?}", ctrlc2::last_signal());
+/// ```
+pub fn last_signal() -> Option<Signal> {
+    match LAST_SIGNAL.load(Ordering::Relaxed) {
+        NO_SIGNAL => None,
+        #[cfg(unix)]
+        raw => Signal::try_from(raw).ok(),
+        #[cfg(not(unix))]
+        raw => Some(raw as Signal),
+    }
+}
+
+/// Count `count` received signals, the last of which was `signal`.
+fn record_received(signal: Signal, count: u64) {
+    LAST_SIGNAL.store(AsRawSignal::as_raw(&signal), Ordering::Relaxed);
+    SIGNALS_RECEIVED.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Pause the delivery of signals to the signal handler.
+///
+/// Signals received while paused are queued, not dropped: the signal handling thread holds on
+/// to the first one, the rest stay queued by the operating system, and all of them are
+/// delivered in order once [`resume()`](fn.resume.html) is called. This keeps Ctrl-C from
+/// interrupting a critical section without unsetting the handler and exposing the process to
+/// the default action of the signals in between.
+///
+/// The pause applies to the handler set now and to handlers set later, until resumed. Signals
+/// waited for with [`wait_for_signal()`](fn.wait_for_signal.html) or received from a
+/// [`RawSignalSource`] are not affected. A paused handler can still be unset, in which case the
+/// queued signals are discarded.
+///
+/// On Windows, closing the console window, logging off and shutting down only give the process
+/// a few seconds, paused or not.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// ctrlc2::pause();
+/// // Write the checkpoint...
+/// ctrlc2::resume();
+/// ```
+pub fn pause() {
+    PAUSED.store(true, Ordering::SeqCst);
+}
+
+/// Resume the delivery of signals paused with [`pause()`](fn.pause.html).
+///
+/// The handler runs for the signals received in the meantime, on the signal handling thread.
+/// Does nothing if the delivery is not paused.
+pub fn resume() {
+    let _guard = lock_init();
+    PAUSED.store(false, Ordering::SeqCst);
+    RESUMED.notify_all();
+}
+
+/// Returns whether the delivery of signals is paused with [`pause()`](fn.pause.html).
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+/// Block the signal handling thread of `source` while the delivery of signals is paused.
+///
+/// Returns `false` if the handler was unset in the meantime.
+fn wait_while_paused(source: &Arc<platform::Source>) -> bool {
+    if !PAUSED.load(Ordering::SeqCst) {
+        return true;
+    }
+    let mut guard = lock_init();
+    while PAUSED.load(Ordering::SeqCst) {
+        match guard.as_ref() {
+            Some(current) if Arc::ptr_eq(current, source) => {}
+            _ => return false,
+        }
+        guard = RESUMED.wait(guard).unwrap_or_else(|e| e.into_inner());
+    }
+    true
+}
+
+/// Unregister the signal handler set by [`set_handler()`](fn.set_handler.html) or
+/// [`try_set_handler()`](fn.try_set_handler.html).
+///
+/// Restores the signal dispositions that were in place before the handler was set and
+/// wakes up the signal handling thread, which then exits without running the handler
+/// again. A handler that is running at the time finishes its current call first. A
+/// `JoinHandle` returned by `set_handler()` can therefore be joined without waiting for
+/// another signal. A new handler may be set afterwards. Signals received but not handled yet,
+/// including those queued while the handler was busy, are discarded along with the handler
+/// and never reach a new one, and [`signals_received()`](fn.signals_received.html) is reset.
+///
+/// Does nothing if no handler is set.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// // Ctrl-C is trapped here...
+/// ctrlc2::unset_handler().expect("Error unsetting Ctrl-C handler");
+/// // ...and terminates the process again here.
+/// handle.join().unwrap();
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while restoring the previous signal
+/// dispositions. The handler is considered unset regardless.
+pub fn unset_handler() -> Result<(), Error> {
+    let mut guard = lock_init();
+
+    if let Some(source) = guard.take() {
+        INIT.store(false, Ordering::Release);
+        SIGNALS_RECEIVED.store(0, Ordering::Relaxed);
+        RESUMED.notify_all();
+        unsafe { platform::deinit_os_handler(&source)? };
+    }
+
+    Ok(())
+}
+
+/// Forget the signal handler inherited from the parent in a child process created via
+/// `fork(2)`.
+///
+/// Only the thread calling `fork(2)` exists in the child, so the signal handling thread of a
+/// handler set in the parent is gone, while the child still sees the handler as set and its
+/// signal handlers stay installed: signals are trapped, but never handled, and setting a new
+/// handler fails with [`Error::MultipleHandlers`]. Call this in the child right after
+/// `fork(2)` to unset the inherited handler like [`unset_handler()`](fn.unset_handler.html)
+/// does, which restores the signal dispositions from before it was set. The self-pipe of the
+/// inherited handler is shared with the parent, so the child only closes its copy of it and
+/// leaves the parent's signal handling thread alone. The handler of the parent can't be carried
+/// over, as it lived on the thread that is gone, so set a new one
+/// afterwards if the child should handle Ctrl-C too.
+///
+/// Does nothing if no handler is set. Children that call `execve(2)` right away don't need
+/// this, as the signal handlers are reset by it anyway. This must not run while another thread
+/// of the parent was setting or unsetting a handler at the time of the fork, as the lock it
+/// held is never released in the child.
+///
+/// Only available on Unix.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// # #[cfg(unix)]
+/// if unsafe { nix::libc::fork() } == 0 {
+///     ctrlc2::reinit_after_fork().expect("Error resetting Ctrl-C handler");
+///     ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// }
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while restoring the previous signal
+/// dispositions. The handler is considered unset regardless.
+#[cfg(unix)]
+pub fn reinit_after_fork() -> Result<(), Error> {
+    // Unsetting never waits for the signal handling thread, and the os handler tells a forked
+    // child apart from the process that installed it.
+    unset_handler()
+}
+
+/// Handle to unset a particular signal handler, see [`shutdown_handle()`](fn.shutdown_handle.html).
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    source: Weak<platform::Source>,
+}
+
+impl ShutdownHandle {
+    /// Unset the signal handler this handle was obtained for, as if by
+    /// [`unset_handler()`](fn.unset_handler.html).
+    ///
+    /// The signal handling thread is woken up and exits without running the handler again, so
+    /// its `JoinHandle` can be joined right away. Does nothing if the handler was already unset,
+    /// even if another handler has been set since.
+    ///
+    /// # Errors
+    /// Will return an error if a system error occurred while restoring the previous signal
+    /// dispositions. The handler is considered unset regardless.
+    pub fn shutdown(&self) -> Result<(), Error> {
+        let mut guard = lock_init();
+        unset_if_current(&mut guard, &self.source).map(|_| ())
+    }
+}
+
+/// Returns a handle to unset the currently set signal handler, or `None` if no handler is set.
+///
+/// Unlike [`unset_handler()`](fn.unset_handler.html), the handle only ever unsets the handler
+/// that was set when it was obtained. It can be passed to the part of a program that decides to
+/// shut down, so the signal handling thread can be joined without waiting for a signal.
+///
+/// # Example
+/// ```no_run
+/// let handle = ctrlc2::set_handler(|| true).expect("Error setting Ctrl-C handler");
+/// let shutdown = ctrlc2::shutdown_handle().unwrap();
+/// // Shutting down for a reason other than Ctrl-C...
+/// shutdown.shutdown().expect("Error unsetting Ctrl-C handler");
+/// handle.join().unwrap();
+/// ```
+pub fn shutdown_handle() -> Option<ShutdownHandle> {
+    let guard = lock_init();
+    guard.as_ref().map(|source| ShutdownHandle {
+        source: Arc::downgrade(source),
+    })
+}
+
+/// Signal handler that is unset when this guard is dropped, see
+/// [`set_handler_scoped()`](fn.set_handler_scoped.html).
+#[derive(Debug)]
+#[must_use = "the handler is unset as soon as the guard is dropped"]
+pub struct HandlerGuard {
+    handle: Option<JoinHandle<()>>,
+    shutdown: ShutdownHandle,
+}
+
+/// Signal handler instance that owns its registration, see [`HandlerGuard::new()`].
+pub type Handler = HandlerGuard;
+
+impl HandlerGuard {
+    /// Register signal handler for Ctrl-C that is unset when the returned instance is dropped.
+    ///
+    /// Same as [`set_handler_scoped()`](fn.set_handler_scoped.html). Instances can be created
+    /// and dropped any number of times within a process, e.g. once per test case or per loaded
+    /// plugin, but only one of them can be registered at a time: creating an instance while
+    /// another one, or any other handler, is set is an error rather than replacing it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// for _ in 0..3 {
+    ///     let handler = ctrlc2::Handler::new(|| true).expect("Error setting Ctrl-C handler");
+    ///     // Ctrl-C is trapped here...
+    ///     drop(handler);
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a
+    /// system error occurred while setting the handler.
+    pub fn new<F>(user_handler: F) -> Result<Self, Error>
+    where
+        F: FnMut() -> bool + 'static + Send,
+    {
+        set_handler_scoped(user_handler)
+    }
+
+    /// Wait for the signal handling thread to exit, which happens once the handler returns
+    /// `true`. The handler is unset afterwards.
+    ///
+    /// # Errors
+    /// Returns the panic payload if the signal handling thread panicked.
+    pub fn join(mut self) -> thread::Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle.join(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for HandlerGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.shutdown.shutdown() {
+            eprintln!("Error unsetting Ctrl-C handler: {}", e);
+        }
+        if let Some(handle) = self.handle.take() {
+            // The guard may be dropped by the handler itself.
+            if handle.thread().id() != thread::current().id() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+/// Register signal handler for Ctrl-C for as long as the returned guard lives.
+///
+/// Works like [`set_handler()`](fn.set_handler.html), but dropping the returned
+/// [`HandlerGuard`] unsets the handler as if by [`ShutdownHandle::shutdown()`], restoring the
+/// previous signal dispositions, and joins the signal handling thread. Errors while unsetting
+/// the handler are reported on stderr instead of panicking.
+///
+/// # Example
+/// ```no_run
+/// {
+///     let _guard = ctrlc2::set_handler_scoped(|| {
+///         println!("Interrupted while busy");
+///         false
+///     })
+///     .expect("Error setting Ctrl-C handler");
+///     // Ctrl-C is trapped here...
+/// }
+/// // ...and terminates the process again here.
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_scoped<F>(mut user_handler: F) -> Result<HandlerGuard, Error>
+where
+    F: FnMut() -> bool + 'static + Send,
+{
+    let (handle, source) = init_and_register(
+        platform::DEFAULT_SIGNALS,
+        move |_| user_handler(),
+        Default::default(),
+        &HandlerConfig::default(),
+    )?;
+    Ok(HandlerGuard {
+        handle: Some(handle),
+        shutdown: ShutdownHandle {
+            source: Arc::downgrade(&source),
+        },
+    })
+}
+
+/// Unset the handler registered with `source` if it is still the current one.
+///
+/// Returns whether it was.
+fn unset_if_current(
+    guard: &mut Option<Arc<platform::Source>>,
+    source: &Weak<platform::Source>,
+) -> Result<bool, Error> {
+    let current = match (guard.as_ref(), source.upgrade()) {
+        (Some(current), Some(source)) => Arc::ptr_eq(current, &source),
+        _ => false,
+    };
+    if current {
+        let source = guard.take().unwrap();
+        INIT.store(false, Ordering::Release);
+        SIGNALS_RECEIVED.store(0, Ordering::Relaxed);
+        RESUMED.notify_all();
+        unsafe { platform::deinit_os_handler(&source)? };
+    }
+    Ok(current)
+}
@@ -13,8 +13,44 @@ mod unix;
 #[cfg(windows)]
 mod windows;
 
+#[cfg(not(any(unix, windows)))]
+mod unsupported;
+
 #[cfg(unix)]
 pub use self::unix::*;
 
+/// Options for registering the os handler.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Replace handlers that are already registered for the signals instead of failing.
+    /// Handler routines are never replaced on Windows.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub overwrite: bool,
+    /// Let the handler routine registered before ours see the events too. Unix signals are
+    /// chained on demand instead, see `chain()`.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub chain: bool,
+    /// Install the Unix signal handlers with `SA_RESTART`.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub restart: bool,
+    /// Register the Unix signal handlers through the registry of the `signal-hook` crate.
+    #[cfg_attr(not(all(unix, feature = "signal-hook")), allow(dead_code))]
+    pub signal_hook: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            overwrite: true,
+            chain: false,
+            restart: true,
+            signal_hook: false,
+        }
+    }
+}
+
 #[cfg(windows)]
 pub use self::windows::*;
+
+#[cfg(not(any(unix, windows)))]
+pub use self::unsupported::*;
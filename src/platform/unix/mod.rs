@@ -12,6 +12,7 @@ use nix::unistd;
 use std::os::fd::BorrowedFd;
 use std::os::fd::IntoRawFd;
 use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 static mut PIPE: (RawFd, RawFd) = (-1, -1);
 
@@ -21,12 +22,114 @@ pub type Error = nix::Error;
 /// Platform specific signal type
 pub type Signal = nix::sys::signal::Signal;
 
-extern "C" fn os_handler(_: nix::libc::c_int) {
+/// Details about a received signal, taken from the `siginfo_t` passed to the os handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigInfo {
+    /// The received signal.
+    pub signal: Signal,
+    /// Why the signal was sent, e.g. `SI_USER` if it was sent by `kill(2)`. See `sigaction(2)`.
+    pub code: nix::libc::c_int,
+    /// Process id of the sender. Only meaningful if the signal was sent by a process, as told
+    /// by `code`.
+    pub pid: nix::libc::pid_t,
+    /// Real user id of the sender. Only meaningful if the signal was sent by a process, as told
+    /// by `code`.
+    pub uid: nix::libc::uid_t,
+    /// Who sent the signal, decoded from `code`.
+    pub origin: SignalOrigin,
+}
+
+impl SigInfo {
+    /// Returns whether the signal was sent by this process itself, e.g. with `raise(3)`.
+    ///
+    /// A handler that re-raises the signal can use this to not handle its own signal again.
+    /// Always `false` where the [`SignalOrigin`] is not decoded.
+    pub fn is_self_sent(&self) -> bool {
+        self.origin.is_process() && self.pid == unistd::getpid().as_raw()
+    }
+}
+
+/// Who sent a signal, decoded from the `si_code` of a [`SigInfo`].
+///
+/// The codes are only decoded on Linux and Android. Elsewhere, every code is reported as
+/// [`Other`](SignalOrigin::Other).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalOrigin {
+    /// Sent by a process with `kill(2)`, `SI_USER`.
+    User,
+    /// Sent by a process with `sigqueue(3)`, `SI_QUEUE`.
+    Queue,
+    /// Sent by a process to one of its threads with `tgkill(2)`, which is what `raise(3)` does,
+    /// `SI_TKILL`.
+    Thread,
+    /// Sent by the kernel, e.g. by the terminal driver when `Ctrl+C` was pressed.
+    Kernel,
+    /// Any other `si_code`.
+    Other(nix::libc::c_int),
+}
+
+impl SignalOrigin {
+    fn from_code(code: nix::libc::c_int) -> Self {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        match code {
+            nix::libc::SI_USER => return SignalOrigin::User,
+            nix::libc::SI_QUEUE => return SignalOrigin::Queue,
+            nix::libc::SI_TKILL => return SignalOrigin::Thread,
+            code if code > 0 => return SignalOrigin::Kernel,
+            _ => {}
+        }
+        SignalOrigin::Other(code)
+    }
+
+    /// Returns whether the signal was sent by a process, so that the `pid` and `uid` of the
+    /// [`SigInfo`] are meaningful.
+    pub fn is_process(&self) -> bool {
+        matches!(
+            self,
+            SignalOrigin::User | SignalOrigin::Queue | SignalOrigin::Thread
+        )
+    }
+}
+
+// Records written to the pipe are the signal number, si_code, si_pid and si_uid, each four
+// bytes in native byte order. Writes of up to PIPE_BUF bytes are atomic, so records never
+// interleave.
+const RECORD_LEN: usize = 16;
+
+extern "C" fn os_handler(
+    sig: nix::libc::c_int,
+    info: *mut nix::libc::siginfo_t,
+    _context: *mut nix::libc::c_void,
+) {
+    unsafe { write_record(sig, info.as_ref()) }
+}
+
+/// Write the record of `sig` to the pipe, from signal context.
+unsafe fn write_record(sig: nix::libc::c_int, info: Option<&nix::libc::siginfo_t>) {
     // Assuming this always succeeds. Can't really handle errors in any meaningful way.
-    unsafe {
-        let fd = BorrowedFd::borrow_raw(PIPE.1);
-        let _ = unistd::write(fd, &[0u8]);
+    if PIPE.1 < 0 {
+        return;
     }
+    let (code, pid, uid) = match info {
+        Some(info) => (info.si_code, info.si_pid(), info.si_uid()),
+        None => (0, 0, 0),
+    };
+    let fd = BorrowedFd::borrow_raw(PIPE.1);
+    let _ = unistd::write(fd, &encode_record(sig, code, pid, uid));
+}
+
+fn encode_record(
+    sig: nix::libc::c_int,
+    code: nix::libc::c_int,
+    pid: nix::libc::pid_t,
+    uid: nix::libc::uid_t,
+) -> [u8; RECORD_LEN] {
+    let mut record = [0u8; RECORD_LEN];
+    record[0..4].copy_from_slice(&sig.to_ne_bytes());
+    record[4..8].copy_from_slice(&code.to_ne_bytes());
+    record[8..12].copy_from_slice(&pid.to_ne_bytes());
+    record[12..16].copy_from_slice(&uid.to_ne_bytes());
+    record
 }
 
 // pipe2(2) is not available on macOS, iOS, AIX or Haiku, so we need to use pipe(2) and fcntl(2)
@@ -81,16 +184,68 @@ fn pipe2(flags: nix::fcntl::OFlag) -> nix::Result<(RawFd, RawFd)> {
     Ok((pipe.0.into_raw_fd(), pipe.1.into_raw_fd()))
 }
 
-/// Register os signal handler.
+/// Signal dispositions replaced by an os handler.
+#[derive(Debug, Clone)]
+pub struct Disposition {
+    actions: Vec<(Signal, nix::sys::signal::SigAction)>,
+}
+
+/// Self-pipe of an installed os handler, together with the dispositions it replaced.
+///
+/// The pipe is closed when the last reference to this struct is dropped, or when the os handler
+/// is uninstalled in a child process created via `fork(2)`.
+pub struct Source {
+    pipe: (RawFd, RawFd),
+    /// Whether the pipe has been closed already.
+    closed: AtomicBool,
+    /// Process that installed the os handler. A forked child shares the pipe with it.
+    pid: nix::libc::pid_t,
+    previous: Disposition,
+    /// Actions registered with `signal-hook` instead of replacing the dispositions.
+    #[cfg(feature = "signal-hook")]
+    hooks: Vec<(Signal, signal_hook_registry::SigId)>,
+}
+
+impl Source {
+    fn close(&self) {
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            let _ = unistd::close(self.pipe.1);
+            let _ = unistd::close(self.pipe.0);
+        }
+    }
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Signals handled when no other signals are requested.
+pub const DEFAULT_SIGNALS: &[Signal] = &[
+    Signal::SIGINT,
+    #[cfg(feature = "termination")]
+    Signal::SIGTERM,
+    #[cfg(feature = "termination")]
+    Signal::SIGHUP,
+    #[cfg(feature = "quit")]
+    Signal::SIGQUIT,
+];
+
+/// Register os signal handler for the given signals.
 ///
 /// Must be called before calling [`block_ctrl_c()`](fn.block_ctrl_c.html)
-/// and should only be called once.
+/// and should only be called once before calling
+/// [`deinit_os_handler()`](fn.deinit_os_handler.html).
 ///
 /// # Errors
 /// Will return an error if a system error occurred.
 ///
 #[inline]
-pub unsafe fn init_os_handler(overwrite: bool) -> Result<(), Error> {
+pub unsafe fn init_os_handler(
+    signals: &[Signal],
+    options: super::Options,
+) -> Result<Source, Error> {
     use nix::fcntl;
     use nix::sys::signal;
 
@@ -101,6 +256,7 @@ pub unsafe fn init_os_handler(overwrite: bool) -> Result<(), Error> {
         // but if it does, there isn't much we can do
         let _ = unistd::close(PIPE.1);
         let _ = unistd::close(PIPE.0);
+        PIPE = (-1, -1);
         e
     };
 
@@ -109,61 +265,503 @@ pub unsafe fn init_os_handler(overwrite: bool) -> Result<(), Error> {
         return Err(close_pipe(e));
     }
 
-    let handler = signal::SigHandler::Handler(os_handler);
-    #[cfg(not(target_os = "nto"))]
-    let new_action = signal::SigAction::new(
-        handler,
-        signal::SaFlags::SA_RESTART,
-        signal::SigSet::empty(),
-    );
+    #[cfg(feature = "signal-hook")]
+    if options.signal_hook {
+        return register_hooks(signals).map_err(close_pipe);
+    }
+
+    let handler = signal::SigHandler::SigAction(os_handler);
+    #[cfg_attr(target_os = "nto", allow(unused_mut))]
+    let mut flags = signal::SaFlags::SA_SIGINFO;
     // SA_RESTART is not supported on QNX Neutrino 7.1 and before
-    #[cfg(target_os = "nto")]
-    let new_action =
-        signal::SigAction::new(handler, signal::SaFlags::empty(), signal::SigSet::empty());
+    #[cfg(not(target_os = "nto"))]
+    if options.restart {
+        flags |= signal::SaFlags::SA_RESTART;
+    }
+    let new_action = signal::SigAction::new(handler, flags, signal::SigSet::empty());
 
-    let sigint_old = match signal::sigaction(signal::Signal::SIGINT, &new_action) {
-        Ok(old) => old,
-        Err(e) => return Err(close_pipe(e)),
+    let restore = |old_actions: &[(Signal, signal::SigAction)]| {
+        for (sig, old) in old_actions.iter().rev() {
+            signal::sigaction(*sig, old).unwrap();
+        }
     };
-    if !overwrite && sigint_old.handler() != signal::SigHandler::SigDfl {
-        signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
-        return Err(close_pipe(nix::Error::EEXIST));
-    }
 
-    #[cfg(feature = "termination")]
-    {
-        let sigterm_old = match signal::sigaction(signal::Signal::SIGTERM, &new_action) {
+    let mut old_actions: Vec<(Signal, signal::SigAction)> = Vec::with_capacity(signals.len());
+    for &sig in signals {
+        if old_actions.iter().any(|(s, _)| *s == sig) {
+            continue;
+        }
+        let old = match signal::sigaction(sig, &new_action) {
             Ok(old) => old,
             Err(e) => {
-                signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
+                restore(&old_actions);
                 return Err(close_pipe(e));
             }
         };
-        if !overwrite && sigterm_old.handler() != signal::SigHandler::SigDfl {
-            signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
-            signal::sigaction(signal::Signal::SIGTERM, &sigterm_old).unwrap();
+        old_actions.push((sig, old));
+        if !options.overwrite && old.handler() != signal::SigHandler::SigDfl {
+            restore(&old_actions);
             return Err(close_pipe(nix::Error::EEXIST));
         }
-        let sighup_old = match signal::sigaction(signal::Signal::SIGHUP, &new_action) {
-            Ok(old) => old,
+    }
+
+    Ok(Source {
+        pipe: PIPE,
+        closed: AtomicBool::new(false),
+        pid: nix::libc::getpid(),
+        previous: Disposition {
+            actions: old_actions,
+        },
+        #[cfg(feature = "signal-hook")]
+        hooks: Vec::new(),
+    })
+}
+
+/// Register actions writing to the pipe with `signal-hook`, which keeps the handlers that were
+/// installed before and runs them as well.
+#[cfg(feature = "signal-hook")]
+unsafe fn register_hooks(signals: &[Signal]) -> Result<Source, Error> {
+    use nix::libc;
+
+    let mut hooks: Vec<(Signal, signal_hook_registry::SigId)> = Vec::with_capacity(signals.len());
+    for &sig in signals {
+        if hooks.iter().any(|(s, _)| *s == sig) {
+            continue;
+        }
+        let action = move |info: &libc::siginfo_t| write_record(sig as libc::c_int, Some(info));
+        match signal_hook_registry::register_sigaction(sig as libc::c_int, action) {
+            Ok(id) => hooks.push((sig, id)),
             Err(e) => {
-                signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
-                signal::sigaction(signal::Signal::SIGTERM, &sigterm_old).unwrap();
-                return Err(close_pipe(e));
+                for &(_, id) in hooks.iter().rev() {
+                    signal_hook_registry::unregister(id);
+                }
+                return Err(nix::Error::from_raw(
+                    e.raw_os_error().unwrap_or(libc::EINVAL),
+                ));
             }
-        };
-        if !overwrite && sighup_old.handler() != signal::SigHandler::SigDfl {
-            signal::sigaction(signal::Signal::SIGINT, &sigint_old).unwrap();
-            signal::sigaction(signal::Signal::SIGTERM, &sigterm_old).unwrap();
-            signal::sigaction(signal::Signal::SIGHUP, &sighup_old).unwrap();
-            return Err(close_pipe(nix::Error::EEXIST));
         }
     }
 
+    Ok(Source {
+        pipe: PIPE,
+        closed: AtomicBool::new(false),
+        pid: libc::getpid(),
+        previous: Disposition {
+            actions: Vec::new(),
+        },
+        hooks,
+    })
+}
+
+/// Returns the first of the given signals whose disposition is not `SIG_DFL`.
+///
+/// The dispositions are only read, not changed.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn existing_handler(signals: &[Signal]) -> Result<Option<Signal>, Error> {
+    use nix::libc;
+
+    for &sig in signals {
+        let mut current: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(sig as libc::c_int, std::ptr::null(), &mut current) != 0 {
+            return Err(nix::Error::last());
+        }
+        if current.sa_sigaction != libc::SIG_DFL {
+            return Ok(Some(sig));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the current disposition of `sig`, read with `sigaction(2)`.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn current_disposition(sig: Signal) -> Result<crate::Disposition, Error> {
+    use nix::libc;
+
+    let mut current: libc::sigaction = std::mem::zeroed();
+    if libc::sigaction(sig as libc::c_int, std::ptr::null(), &mut current) != 0 {
+        return Err(nix::Error::last());
+    }
+    Ok(match current.sa_sigaction {
+        libc::SIG_DFL => crate::Disposition::Default,
+        libc::SIG_IGN => crate::Disposition::Ignored,
+        handler if handler == os_handler as *const () as libc::sighandler_t => {
+            crate::Disposition::Own
+        }
+        _ => crate::Disposition::Custom,
+    })
+}
+
+/// Returns the signals the os handler of `source` is installed for.
+#[inline]
+pub fn signals(source: &Source) -> Vec<Signal> {
+    let signals = source.previous.actions.iter().map(|&(sig, _)| sig);
+    #[cfg(feature = "signal-hook")]
+    let signals = signals.chain(source.hooks.iter().map(|&(sig, _)| sig));
+    signals.collect()
+}
+
+/// Returns the signal named `name`, like `SIGINT`, or with the number `name`.
+///
+/// Names are case-insensitive, and the `SIG` prefix is optional.
+pub fn signal_from_name(name: &str) -> Option<Signal> {
+    if let Ok(number) = name.parse::<nix::libc::c_int>() {
+        return Signal::try_from(number).ok();
+    }
+    let name = name.to_ascii_uppercase();
+    match name.strip_prefix("SIG") {
+        Some(_) => name.parse().ok(),
+        None => format!("SIG{}", name).parse().ok(),
+    }
+}
+
+/// Returns the name of `sig`, like `SIGINT`.
+pub fn signal_name(sig: Signal) -> String {
+    sig.as_str().into()
+}
+
+/// Whether signals of the same kind can be merged before the os handler sees them. The kernel
+/// keeps at most one instance of each standard signal pending.
+pub const COALESCES_SIGNALS: bool = true;
+
+/// Returns whether a handler can be installed for `sig`, i.e. all signals but `SIGKILL` and
+/// `SIGSTOP`.
+pub fn is_supported(sig: Signal) -> bool {
+    !matches!(sig, Signal::SIGKILL | Signal::SIGSTOP)
+}
+
+/// Signals only sent by a terminal, see [`has_controlling_terminal()`].
+pub const TERMINAL_SIGNALS: &[Signal] = &[Signal::SIGINT, Signal::SIGQUIT];
+
+/// Returns whether a debugger is attached, from the `TracerPid` in `/proc/self/status`.
+///
+/// Returns `None` if that can't be read, and on other systems than Linux and Android.
+pub fn debugger_attached() -> Option<bool> {
+    if !cfg!(any(target_os = "linux", target_os = "android")) {
+        return None;
+    }
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+        .map(|pid| pid != 0)
+}
+
+/// Returns whether the process has a controlling terminal, which could send it `SIGINT`.
+#[inline]
+pub fn has_controlling_terminal() -> bool {
+    use nix::fcntl::{self, OFlag};
+    use nix::sys::stat::Mode;
+
+    let flags = OFlag::O_RDONLY | OFlag::O_NOCTTY | OFlag::O_CLOEXEC;
+    match fcntl::open("/dev/tty", flags, Mode::empty()) {
+        Ok(fd) => {
+            let _ = unistd::close(fd);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Returns the signal dispositions replaced by [`init_os_handler()`](fn.init_os_handler.html).
+#[inline]
+pub fn previous_disposition(source: &Source) -> Disposition {
+    source.previous.clone()
+}
+
+/// Invoke the disposition that was replaced for `sig`.
+///
+/// The replaced disposition is temporarily re-installed and the signal is raised again.
+/// Signals arriving in the meantime are handled by the replaced disposition as well. Does
+/// nothing for signals registered with `signal-hook`, which runs the replaced handlers itself.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn chain(source: &Source, sig: Signal) -> Result<(), Error> {
+    use nix::sys::signal;
+
+    let old = match source.previous.actions.iter().find(|(s, _)| *s == sig) {
+        Some((_, old)) => old,
+        None => return Ok(()),
+    };
+    let ours = signal::sigaction(sig, old)?;
+    let result = signal::raise(sig);
+    signal::sigaction(sig, &ours)?;
+    result
+}
+
+/// Tell the os handler that the user handler for `sig` has returned.
+///
+/// The os handler never waits for the user handler on Unix, so this does nothing.
+#[inline]
+pub unsafe fn signal_handled(_source: &Source, _sig: Signal) {}
+
+/// Re-install previously saved signal dispositions.
+///
+/// # Errors
+/// Will return an error if a system error occurred. An attempt to restore every
+/// disposition is made regardless.
+///
+#[inline]
+pub unsafe fn restore_disposition(disposition: &Disposition) -> Result<(), Error> {
+    use nix::sys::signal;
+
+    let mut result = Ok(());
+    for (sig, old) in disposition.actions.iter().rev() {
+        if let Err(e) = signal::sigaction(*sig, old) {
+            result = result.and(Err(e));
+        }
+    }
+    result
+}
+
+/// Set the disposition of the given signals to `SIG_IGN`.
+///
+/// # Errors
+/// Will return an error if a system error occurred. The signals before the failing one are
+/// ignored regardless.
+///
+#[inline]
+pub unsafe fn ignore(signals: &[Signal]) -> Result<(), Error> {
+    set_disposition(signals, nix::sys::signal::SigHandler::SigIgn)
+}
+
+/// Raise `SIGINT` on the calling thread with `raise(3)`.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+pub fn raise_ctrl_c() -> Result<(), Error> {
+    nix::sys::signal::raise(Signal::SIGINT)
+}
+
+/// Set the disposition of `SIGPIPE` to `SIG_IGN`.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+pub fn ignore_sigpipe() -> Result<(), Error> {
+    unsafe { ignore(&[Signal::SIGPIPE]) }
+}
+
+/// Set the disposition of the given signals to `SIG_IGN` and return the replaced dispositions,
+/// for [`restore_disposition()`](fn.restore_disposition.html).
+///
+/// # Errors
+/// Will return an error if a system error occurred. The replaced dispositions are restored in
+/// that case.
+///
+#[cfg(all(
+    feature = "kqueue",
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly"
+    )
+))]
+pub unsafe fn ignore_saving(signals: &[Signal]) -> Result<Disposition, Error> {
+    use nix::sys::signal;
+
+    let action = signal::SigAction::new(
+        signal::SigHandler::SigIgn,
+        signal::SaFlags::empty(),
+        signal::SigSet::empty(),
+    );
+    let mut previous = Disposition {
+        actions: Vec::with_capacity(signals.len()),
+    };
+    for &sig in signals {
+        match signal::sigaction(sig, &action) {
+            Ok(old) => previous.actions.push((sig, old)),
+            Err(e) => {
+                let _ = restore_disposition(&previous);
+                return Err(e);
+            }
+        }
+    }
+    Ok(previous)
+}
+
+/// Set the disposition of the given signals to `SIG_DFL`.
+///
+/// # Errors
+/// Will return an error if a system error occurred. The signals before the failing one are
+/// reset regardless.
+///
+#[inline]
+pub unsafe fn reset(signals: &[Signal]) -> Result<(), Error> {
+    set_disposition(signals, nix::sys::signal::SigHandler::SigDfl)
+}
+
+unsafe fn set_disposition(
+    signals: &[Signal],
+    handler: nix::sys::signal::SigHandler,
+) -> Result<(), Error> {
+    use nix::sys::signal;
+
+    let action = signal::SigAction::new(handler, signal::SaFlags::empty(), signal::SigSet::empty());
+    for &sig in signals {
+        signal::sigaction(sig, &action)?;
+    }
     Ok(())
 }
 
-/// Blocks until a Ctrl-C signal is received.
+/// Send `sig` to `pid` with `kill(2)`.
+///
+/// Processes that exited in the meantime are not an error.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+pub fn forward(pid: i32, sig: Signal) -> Result<(), Error> {
+    match nix::sys::signal::kill(unistd::Pid::from_raw(pid), sig) {
+        Err(nix::errno::Errno::ESRCH) => Ok(()),
+        result => result,
+    }
+}
+
+/// Unblock `signals` on the calling thread with `pthread_sigmask(3)`.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+pub fn unblock_on_current_thread(signals: &[Signal]) -> Result<(), Error> {
+    let mut mask = nix::sys::signal::SigSet::empty();
+    for &sig in signals {
+        mask.add(sig);
+    }
+    mask.thread_unblock()
+}
+
+/// Returns the conventional `128 + signum` exit status of a process terminated by `sig`.
+#[inline]
+pub fn exit_code(sig: Signal) -> i32 {
+    128 + sig as i32
+}
+
+/// Reset the disposition of `sig` to `SIG_DFL` and raise it on the calling thread.
+///
+/// Exits with the conventional `128 + signum` status if the signal does not terminate the
+/// process, which only happens if it is not a terminating signal.
+pub fn re_raise(sig: Signal) -> ! {
+    use nix::sys::signal;
+
+    let default = signal::SigAction::new(
+        signal::SigHandler::SigDfl,
+        signal::SaFlags::empty(),
+        signal::SigSet::empty(),
+    );
+    let mut mask = signal::SigSet::empty();
+    mask.add(sig);
+    unsafe {
+        let _ = signal::sigaction(sig, &default);
+    }
+    let _ = mask.thread_unblock();
+    let _ = signal::raise(sig);
+
+    std::process::exit(exit_code(sig))
+}
+
+/// Restore the signal dispositions replaced by
+/// [`init_os_handler()`](fn.init_os_handler.html) and wake up any thread blocked in
+/// [`block_ctrl_c()`](fn.block_ctrl_c.html). In a child process created via `fork(2)`, the pipe
+/// shared with the parent is closed instead of waking up the reader, which is the parent's.
+///
+/// # Errors
+/// Will return an error if a system error occurred. An attempt to restore every
+/// disposition is made regardless.
+///
+#[inline]
+pub unsafe fn deinit_os_handler(source: &Source) -> Result<(), Error> {
+    let result = restore_disposition(&source.previous);
+    #[cfg(feature = "signal-hook")]
+    for &(_, id) in &source.hooks {
+        signal_hook_registry::unregister(id);
+    }
+    if PIPE.1 == source.pipe.1 {
+        PIPE = (-1, -1);
+    }
+
+    // In a forked child, the pipe is shared with the parent, whose reader must keep going. The
+    // signal handling thread doesn't exist here, so only close our copies of the pipe.
+    if source.pid != nix::libc::getpid() {
+        source.close();
+        return result;
+    }
+
+    // Zero is not a valid signal number, so it is used to tell the reader to stop.
+    let fd = BorrowedFd::borrow_raw(source.pipe.1);
+    let _ = unistd::write(fd, &encode_record(0, 0, 0, 0));
+
+    result
+}
+
+/// Returns whether [`block_ctrl_c()`](fn.block_ctrl_c.html) would return right away.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn is_pending(source: &Source) -> Result<bool, Error> {
+    use nix::libc;
+
+    let mut fd = libc::pollfd {
+        fd: source.pipe.0,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    loop {
+        match libc::poll(&mut fd, 1, 0) {
+            -1 if nix::Error::last() == nix::Error::EINTR => {}
+            -1 => return Err(nix::Error::last()),
+            n => return Ok(n > 0),
+        }
+    }
+}
+
+/// Queue `sig` for [`block_ctrl_c()`](fn.block_ctrl_c.html) as if it had been sent by this
+/// process with `kill(2)`, without involving the os.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[cfg(feature = "testing")]
+#[inline]
+pub unsafe fn simulate(source: &Source, sig: Signal) -> Result<(), Error> {
+    let record = encode_record(
+        sig as nix::libc::c_int,
+        0,
+        nix::libc::getpid(),
+        nix::libc::getuid(),
+    );
+    unistd::write(BorrowedFd::borrow_raw(source.pipe.1), &record)?;
+    Ok(())
+}
+
+/// Returns the read end of the self-pipe, which is readable whenever
+/// [`block_ctrl_c()`](fn.block_ctrl_c.html) would return right away.
+#[inline]
+pub fn raw_fd(source: &Source) -> RawFd {
+    source.pipe.0
+}
+
+/// Blocks until a Ctrl-C signal is received and returns the received signal.
+///
+/// Returns `None` if the wait was ended by
+/// [`deinit_os_handler()`](fn.deinit_os_handler.html) instead of a signal.
 ///
 /// Must be called after calling [`init_os_handler()`](fn.init_os_handler.html).
 ///
@@ -171,21 +769,44 @@ pub unsafe fn init_os_handler(overwrite: bool) -> Result<(), Error> {
 /// Will return an error if a system error occurred.
 ///
 #[inline]
-pub unsafe fn block_ctrl_c() -> Result<(), CtrlcError> {
+pub unsafe fn block_ctrl_c(source: &Source) -> Result<Option<Signal>, CtrlcError> {
+    Ok(block_ctrl_c_info(source)?.map(|info| info.signal))
+}
+
+/// Blocks until a Ctrl-C signal is received and returns the details about the received signal.
+///
+/// Works like [`block_ctrl_c()`](fn.block_ctrl_c.html).
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn block_ctrl_c_info(source: &Source) -> Result<Option<SigInfo>, CtrlcError> {
     use std::io;
-    let mut buf = [0u8];
+    let mut buf = [0u8; RECORD_LEN];
+    let mut len = 0;
 
     // TODO: Can we safely convert the pipe fd into a std::io::Read
     // with std::os::unix::io::FromRawFd, this would handle EINTR
     // and everything for us.
-    loop {
-        match unistd::read(PIPE.0, &mut buf[..]) {
-            Ok(1) => break,
-            Ok(_) => return Err(CtrlcError::System(io::ErrorKind::UnexpectedEof.into())),
+    while len < RECORD_LEN {
+        match unistd::read(source.pipe.0, &mut buf[len..]) {
+            Ok(0) => return Err(CtrlcError::System(io::ErrorKind::UnexpectedEof.into())),
+            Ok(n) => len += n,
             Err(nix::errno::Errno::EINTR) => {}
             Err(e) => return Err(e.into()),
         }
     }
 
-    Ok(())
+    let field = |i: usize| [buf[i], buf[i + 1], buf[i + 2], buf[i + 3]];
+    match nix::libc::c_int::from_ne_bytes(field(0)) {
+        0 => Ok(None),
+        sig => Ok(Some(SigInfo {
+            signal: Signal::try_from(sig)?,
+            code: nix::libc::c_int::from_ne_bytes(field(4)),
+            origin: SignalOrigin::from_code(nix::libc::c_int::from_ne_bytes(field(4))),
+            pid: nix::libc::pid_t::from_ne_bytes(field(8)),
+            uid: nix::libc::uid_t::from_ne_bytes(field(12)),
+        })),
+    }
 }
@@ -0,0 +1,260 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+//! Stub for platforms without signals, like `wasm32-unknown-unknown` and WASI.
+//!
+//! Everything compiles, but installing an os handler fails with an error of kind
+//! [`Unsupported`](io::ErrorKind::Unsupported).
+
+use std::io;
+
+/// Platform specific error type
+pub type Error = io::Error;
+
+/// Platform specific signal type
+pub type Signal = i32;
+
+/// The conventional number of `SIGINT`, which is never received.
+const SIGINT: Signal = 2;
+
+fn unsupported() -> Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "signal handling is not supported on this platform",
+    )
+}
+
+/// Handler state replaced by an os handler, of which there is none.
+#[derive(Debug, Clone)]
+pub struct Disposition;
+
+/// An installed os handler, which can't exist on this platform.
+pub struct Source {
+    _private: (),
+}
+
+/// Signals handled when no other signals are requested.
+pub const DEFAULT_SIGNALS: &[Signal] = &[SIGINT];
+
+/// Returns the signal named `name`, only `SIGINT`, or with the number `name`.
+pub fn signal_from_name(name: &str) -> Option<Signal> {
+    match name.to_ascii_uppercase().as_str() {
+        "SIGINT" | "INT" => Some(SIGINT),
+        name => name.parse().ok(),
+    }
+}
+
+/// Returns the name of `sig`, or its number for signals other than `SIGINT`.
+pub fn signal_name(sig: Signal) -> String {
+    match sig {
+        SIGINT => "SIGINT".into(),
+        sig => sig.to_string(),
+    }
+}
+
+/// No signals are received on this platform, so none are merged either.
+pub const COALESCES_SIGNALS: bool = false;
+
+/// Returns `true`, installing any handler fails with [`ErrorKind::Unsupported`] instead.
+pub fn is_supported(_sig: Signal) -> bool {
+    true
+}
+
+/// Signals only sent by a terminal, none on this platform.
+pub const TERMINAL_SIGNALS: &[Signal] = &[];
+
+/// Register os signal handler, which is not supported on this platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+#[inline]
+pub unsafe fn init_os_handler(
+    _signals: &[Signal],
+    _options: super::Options,
+) -> Result<Source, Error> {
+    Err(unsupported())
+}
+
+/// Returns the handler state replaced by [`init_os_handler()`](fn.init_os_handler.html).
+#[inline]
+pub fn previous_disposition(_source: &Source) -> Disposition {
+    Disposition
+}
+
+/// Returns the first of the given signals that already has a handler, never any.
+///
+/// # Errors
+/// Never fails.
+///
+#[inline]
+pub unsafe fn existing_handler(_signals: &[Signal]) -> Result<Option<Signal>, Error> {
+    Ok(None)
+}
+
+/// Invoke the handler registered before ours for `sig`, of which there is none.
+///
+/// # Errors
+/// Never fails.
+///
+#[inline]
+pub unsafe fn chain(_source: &Source, _sig: Signal) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Tell the os handler that the user handler for `sig` has returned.
+#[inline]
+pub unsafe fn signal_handled(_source: &Source, _sig: Signal) {}
+
+/// Re-install previously saved handler state.
+///
+/// # Errors
+/// Never fails.
+///
+#[inline]
+pub unsafe fn restore_disposition(_disposition: &Disposition) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Ignore the given signals, which is not supported on this platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+#[inline]
+pub unsafe fn ignore(_signals: &[Signal]) -> Result<(), Error> {
+    Err(unsupported())
+}
+
+/// Returns the disposition of `sig`, always the default one, as signals are not supported on
+/// this platform.
+///
+/// # Errors
+/// Never fails.
+///
+#[inline]
+pub unsafe fn current_disposition(_sig: Signal) -> Result<crate::Disposition, Error> {
+    Ok(crate::Disposition::Default)
+}
+
+/// Stop ignoring the given signals, which is not supported on this platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+#[inline]
+pub unsafe fn reset(_signals: &[Signal]) -> Result<(), Error> {
+    Err(unsupported())
+}
+
+/// Raise Ctrl-C, which is not supported on this platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+pub fn raise_ctrl_c() -> Result<(), Error> {
+    Err(unsupported())
+}
+
+/// Does nothing, there is no `SIGPIPE` on this platform.
+///
+/// # Errors
+/// Never fails.
+///
+pub fn ignore_sigpipe() -> Result<(), Error> {
+    Ok(())
+}
+
+/// Does nothing, there are no signals to unblock on this platform.
+///
+/// # Errors
+/// Never fails.
+///
+pub fn unblock_on_current_thread(_signals: &[Signal]) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Forward `sig` to `pid`, which is not supported on this platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+pub fn forward(_pid: i32, _sig: Signal) -> Result<(), Error> {
+    Err(unsupported())
+}
+
+/// Returns the signals the os handler of `source` is installed for.
+#[inline]
+pub fn signals(_source: &Source) -> Vec<Signal> {
+    Vec::new()
+}
+
+/// Returns `None`, debuggers can't be detected on this platform.
+pub fn debugger_attached() -> Option<bool> {
+    None
+}
+
+/// Returns whether the process has a controlling terminal, always `true` on this platform.
+#[inline]
+pub fn has_controlling_terminal() -> bool {
+    true
+}
+
+/// Returns the `128 + signum` exit status of a shell.
+#[inline]
+pub fn exit_code(sig: Signal) -> i32 {
+    128 + sig
+}
+
+/// Exit the process with the `128 + signum` exit status of a shell.
+pub fn re_raise(sig: Signal) -> ! {
+    std::process::exit(exit_code(sig))
+}
+
+/// Uninstall the os handler of `source`.
+///
+/// # Errors
+/// Never fails.
+///
+#[inline]
+pub unsafe fn deinit_os_handler(_source: &Source) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Blocks until a signal is received, which is not supported on this platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+#[inline]
+pub unsafe fn block_ctrl_c(_source: &Source) -> Result<Option<Signal>, Error> {
+    Err(unsupported())
+}
+
+/// Returns whether [`block_ctrl_c()`](fn.block_ctrl_c.html) would return right away.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+#[inline]
+pub unsafe fn is_pending(_source: &Source) -> Result<bool, Error> {
+    Err(unsupported())
+}
+
+/// Queue `sig` for [`block_ctrl_c()`](fn.block_ctrl_c.html), which is not supported on this
+/// platform.
+///
+/// # Errors
+/// Always returns an error of kind [`Unsupported`](io::ErrorKind::Unsupported).
+///
+#[cfg(feature = "testing")]
+#[inline]
+pub unsafe fn simulate(_source: &Source, _sig: Signal) -> Result<(), Error> {
+    Err(unsupported())
+}
@@ -7,12 +7,22 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
+use std::collections::VecDeque;
 use std::io;
 use std::ptr;
-use windows_sys::Win32::Foundation::{CloseHandle, BOOL, HANDLE, WAIT_FAILED, WAIT_OBJECT_0};
-use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use windows_sys::Win32::Foundation::{
+    CloseHandle, BOOL, HANDLE, STATUS_CONTROL_C_EXIT, WAIT_FAILED, WAIT_OBJECT_0, WAIT_TIMEOUT,
+};
+use windows_sys::Win32::System::Console::{
+    GenerateConsoleCtrlEvent, SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT,
+    CTRL_C_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT,
+};
+use windows_sys::Win32::System::Diagnostics::Debug::IsDebuggerPresent;
 use windows_sys::Win32::System::Threading::{
-    CreateSemaphoreA, ReleaseSemaphore, WaitForSingleObject, INFINITE,
+    CreateEventA, CreateSemaphoreA, ReleaseSemaphore, ResetEvent, SetEvent, WaitForSingleObject,
+    INFINITE,
 };
 
 /// Platform specific error type
@@ -21,43 +31,492 @@ pub type Error = io::Error;
 /// Platform specific signal type
 pub type Signal = u32;
 
+/// Console control event received by the handler routine, see [HandlerRoutine].
+///
+/// [HandlerRoutine]: https://learn.microsoft.com/en-us/windows/console/handlerroutine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowsEvent {
+    /// `CTRL_C_EVENT`, Ctrl+C was pressed.
+    CtrlC,
+    /// `CTRL_BREAK_EVENT`, Ctrl+Break was pressed.
+    CtrlBreak,
+    /// `CTRL_CLOSE_EVENT`, the console window is being closed.
+    Close,
+    /// `CTRL_LOGOFF_EVENT`, the user is logging off. Only received by services.
+    Logoff,
+    /// `CTRL_SHUTDOWN_EVENT`, the system is shutting down. Only received by services.
+    Shutdown,
+    /// Any other event, with its `dwCtrlType` value.
+    Other(u32),
+}
+
+impl WindowsEvent {
+    /// Returns the `dwCtrlType` value of the event.
+    pub fn as_raw(self) -> Signal {
+        match self {
+            WindowsEvent::CtrlC => CTRL_C_EVENT,
+            WindowsEvent::CtrlBreak => CTRL_BREAK_EVENT,
+            WindowsEvent::Close => CTRL_CLOSE_EVENT,
+            WindowsEvent::Logoff => CTRL_LOGOFF_EVENT,
+            WindowsEvent::Shutdown => CTRL_SHUTDOWN_EVENT,
+            WindowsEvent::Other(ctrl_type) => ctrl_type,
+        }
+    }
+}
+
+impl From<Signal> for WindowsEvent {
+    fn from(ctrl_type: Signal) -> Self {
+        match ctrl_type {
+            CTRL_C_EVENT => WindowsEvent::CtrlC,
+            CTRL_BREAK_EVENT => WindowsEvent::CtrlBreak,
+            CTRL_CLOSE_EVENT => WindowsEvent::Close,
+            CTRL_LOGOFF_EVENT => WindowsEvent::Logoff,
+            CTRL_SHUTDOWN_EVENT => WindowsEvent::Shutdown,
+            other => WindowsEvent::Other(other),
+        }
+    }
+}
+
 const MAX_SEM_COUNT: i32 = 255;
 static mut SEMAPHORE: HANDLE = 0 as HANDLE;
+// Set once a close, logoff or shutdown event has been handled.
+static mut HANDLED_EVENT: HANDLE = 0 as HANDLE;
+// Manual-reset event that is set while EVENTS is not empty.
+static mut READY_EVENT: HANDLE = 0 as HANDLE;
+// How long the handler routine waits for a close, logoff or shutdown event to be handled.
+// Windows terminates the process once the routine returns, and after about five seconds even
+// if it doesn't.
+const CLOSE_TIMEOUT_MS: u32 = 5000;
+// Bit mask of the console control events the handler routine handles.
+static HANDLED: AtomicU32 = AtomicU32::new(0);
+// Whether the handler routine lets the next registered routine handle events too.
+static CHAIN: AtomicBool = AtomicBool::new(false);
+// Set once the handler routine has received a handled event since it was registered.
+static REACHED: AtomicBool = AtomicBool::new(false);
+// Console control events received but not yet returned by block_ctrl_c().
+static EVENTS: Mutex<VecDeque<Signal>> = Mutex::new(VecDeque::new());
+// Bit mask of the console control events ignore_routine() ignores.
+static IGNORED: AtomicU32 = AtomicU32::new(0);
+// Held while ignore_routine() is registered or removed.
+static IGNORE_LOCK: Mutex<()> = Mutex::new(());
 const TRUE: BOOL = 1;
 const FALSE: BOOL = 0;
 
-unsafe extern "system" fn os_handler(_: u32) -> BOOL {
+fn event_bit(ctrl_type: u32) -> u32 {
+    1u32.checked_shl(ctrl_type).unwrap_or(0)
+}
+
+fn is_close_event(ctrl_type: u32) -> bool {
+    matches!(
+        ctrl_type,
+        CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT
+    )
+}
+
+unsafe extern "system" fn os_handler(ctrl_type: u32) -> BOOL {
+    if SEMAPHORE.is_null() || HANDLED.load(Ordering::SeqCst) & event_bit(ctrl_type) == 0 {
+        // Let the next handler routine, or the default one, deal with it.
+        return FALSE;
+    }
+    REACHED.store(true, Ordering::SeqCst);
+    // The handler routine runs on its own thread, so taking a lock is fine here.
+    let mut events = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    events.push_back(ctrl_type);
+    SetEvent(READY_EVENT);
+    drop(events);
     // Assuming this always succeeds. Can't really handle errors in any meaningful way.
     ReleaseSemaphore(SEMAPHORE, 1, ptr::null_mut());
-    TRUE
+    if is_close_event(ctrl_type) {
+        // The process is terminated as soon as we return, so give the handler a chance to run.
+        WaitForSingleObject(HANDLED_EVENT, CLOSE_TIMEOUT_MS);
+        return TRUE;
+    }
+    if CHAIN.load(Ordering::SeqCst) {
+        FALSE
+    } else {
+        TRUE
+    }
 }
 
-/// Register os signal handler.
+unsafe extern "system" fn ignore_routine(ctrl_type: u32) -> BOOL {
+    if IGNORED.load(Ordering::SeqCst) & event_bit(ctrl_type) != 0 {
+        TRUE
+    } else {
+        FALSE
+    }
+}
+
+/// Console handler state replaced by an os handler.
+///
+/// Handler routines are stacked on Windows, so installing ours doesn't replace any other
+/// routine and there is nothing to save.
+#[derive(Debug, Clone)]
+pub struct Disposition;
+
+/// Semaphore of an installed os handler, together with the event set once a close, logoff or
+/// shutdown event has been handled, and the event set while received events are pending.
+///
+/// The handles are closed when the last reference to this struct is dropped.
+pub struct Source {
+    semaphore: HANDLE,
+    handled: HANDLE,
+    ready: HANDLE,
+    woken: AtomicBool,
+    signals: Vec<Signal>,
+}
+
+// The semaphore handle may be used from any thread.
+unsafe impl Send for Source {}
+unsafe impl Sync for Source {}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.ready);
+            CloseHandle(self.handled);
+            CloseHandle(self.semaphore);
+        }
+    }
+}
+
+/// Console control events handled when no other events are requested.
+pub const DEFAULT_SIGNALS: &[Signal] = &[
+    CTRL_C_EVENT,
+    CTRL_BREAK_EVENT,
+    #[cfg(feature = "termination")]
+    CTRL_CLOSE_EVENT,
+    #[cfg(feature = "termination")]
+    CTRL_LOGOFF_EVENT,
+    #[cfg(feature = "termination")]
+    CTRL_SHUTDOWN_EVENT,
+];
+
+/// Register os signal handler for the given console control events.
 ///
 /// Must be called before calling [`block_ctrl_c()`](fn.block_ctrl_c.html)
-/// and should only be called once.
+/// and should only be called once before calling
+/// [`deinit_os_handler()`](fn.deinit_os_handler.html).
 ///
 /// # Errors
 /// Will return an error if a system error occurred.
 ///
 #[inline]
-pub unsafe fn init_os_handler(_overwrite: bool) -> Result<(), Error> {
+pub unsafe fn init_os_handler(
+    signals: &[Signal],
+    options: super::Options,
+) -> Result<Source, Error> {
+    let close_handles = |e: io::Error| -> Error {
+        for handle in [
+            ptr::addr_of_mut!(READY_EVENT),
+            ptr::addr_of_mut!(HANDLED_EVENT),
+            ptr::addr_of_mut!(SEMAPHORE),
+        ] {
+            if !(*handle).is_null() {
+                CloseHandle(*handle);
+                *handle = 0 as HANDLE;
+            }
+        }
+        e
+    };
+
     SEMAPHORE = CreateSemaphoreA(ptr::null_mut(), 0, MAX_SEM_COUNT, ptr::null());
     if SEMAPHORE.is_null() {
         return Err(io::Error::last_os_error());
     }
+    HANDLED_EVENT = CreateEventA(ptr::null_mut(), FALSE, FALSE, ptr::null());
+    if HANDLED_EVENT.is_null() {
+        return Err(close_handles(io::Error::last_os_error()));
+    }
+    READY_EVENT = CreateEventA(ptr::null_mut(), TRUE, FALSE, ptr::null());
+    if READY_EVENT.is_null() {
+        return Err(close_handles(io::Error::last_os_error()));
+    }
+    EVENTS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    let handled = signals.iter().fold(0, |mask, &sig| mask | event_bit(sig));
+    HANDLED.store(handled, Ordering::SeqCst);
+    CHAIN.store(options.chain, Ordering::SeqCst);
+    REACHED.store(false, Ordering::SeqCst);
 
     if SetConsoleCtrlHandler(Some(os_handler), TRUE) == FALSE {
-        let e = io::Error::last_os_error();
-        CloseHandle(SEMAPHORE);
-        SEMAPHORE = 0 as HANDLE;
-        return Err(e);
+        return Err(close_handles(io::Error::last_os_error()));
     }
 
+    Ok(Source {
+        semaphore: SEMAPHORE,
+        handled: HANDLED_EVENT,
+        ready: READY_EVENT,
+        woken: AtomicBool::new(false),
+        signals: signals.to_vec(),
+    })
+}
+
+/// Returns the console handler state replaced by [`init_os_handler()`](fn.init_os_handler.html).
+#[inline]
+pub fn previous_disposition(_source: &Source) -> Disposition {
+    Disposition
+}
+
+/// Returns the first of the given events that already has a handler routine registered.
+///
+/// The registered handler routines cannot be queried on Windows, and they are stacked instead
+/// of replaced anyway, so no conflict is ever reported.
+///
+/// # Errors
+/// Never fails on Windows.
+///
+#[inline]
+pub unsafe fn existing_handler(_signals: &[Signal]) -> Result<Option<Signal>, Error> {
+    Ok(None)
+}
+
+/// Invoke the handler routine registered before ours for `sig`.
+///
+/// Handler routines can only be chained by returning `FALSE` from ours, which happens as soon
+/// as the event arrives if [`Options::chain`](super::Options) was set. There is nothing left
+/// to do here.
+///
+/// # Errors
+/// Never fails on Windows.
+///
+#[inline]
+pub unsafe fn chain(_source: &Source, _sig: Signal) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Tell the handler routine that the user handler for `sig` has returned.
+///
+/// For close, logoff and shutdown events, the handler routine waits for this before returning,
+/// because Windows terminates the process right afterwards. Other events are not waited for.
+#[inline]
+pub unsafe fn signal_handled(source: &Source, sig: Signal) {
+    if is_close_event(sig) {
+        SetEvent(source.handled);
+    }
+}
+
+/// Re-install previously saved console handler state.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn restore_disposition(_disposition: &Disposition) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Ignore the given console control events with a handler routine that does nothing.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+pub unsafe fn ignore(signals: &[Signal]) -> Result<(), Error> {
+    let _guard = IGNORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if IGNORED.load(Ordering::SeqCst) == 0
+        && SetConsoleCtrlHandler(Some(ignore_routine), TRUE) == FALSE
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let bits = signals.iter().fold(0, |bits, &sig| bits | event_bit(sig));
+    IGNORED.fetch_or(bits, Ordering::SeqCst);
     Ok(())
 }
 
-/// Blocks until a Ctrl-C signal is received.
+/// Returns whether the handler routine of this crate or [`ignore()`](fn.ignore.html) handles
+/// `sig`, as the registered handler routines can't be queried.
+///
+/// # Errors
+/// Never fails on Windows.
+///
+#[inline]
+pub unsafe fn current_disposition(sig: Signal) -> Result<crate::Disposition, Error> {
+    Ok(
+        if !SEMAPHORE.is_null() && HANDLED.load(Ordering::SeqCst) & event_bit(sig) != 0 {
+            crate::Disposition::Own
+        } else if IGNORED.load(Ordering::SeqCst) & event_bit(sig) != 0 {
+            crate::Disposition::Ignored
+        } else {
+            crate::Disposition::Default
+        },
+    )
+}
+
+/// Stop ignoring the given console control events, see [`ignore()`](fn.ignore.html).
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+pub unsafe fn reset(signals: &[Signal]) -> Result<(), Error> {
+    let _guard = IGNORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let bits = signals.iter().fold(0, |bits, &sig| bits | event_bit(sig));
+    let previous = IGNORED.fetch_and(!bits, Ordering::SeqCst);
+    if previous != 0
+        && previous & !bits == 0
+        && SetConsoleCtrlHandler(Some(ignore_routine), FALSE) == FALSE
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Send `CTRL_C_EVENT` to the processes attached to the console with
+/// `GenerateConsoleCtrlEvent()`.
+///
+/// # Errors
+/// Will return an error if a system error occurred, e.g. because the process has no console.
+///
+pub fn raise_ctrl_c() -> Result<(), Error> {
+    if unsafe { GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Does nothing, there is no `SIGPIPE` on Windows.
+///
+/// # Errors
+/// Never returns an error.
+///
+pub fn ignore_sigpipe() -> Result<(), Error> {
+    Ok(())
+}
+
+/// Does nothing, the handler routine runs on a thread of its own anyway.
+///
+/// # Errors
+/// Never returns an error.
+///
+pub fn unblock_on_current_thread(_signals: &[Signal]) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Does nothing, console control events reach every process attached to the console.
+///
+/// # Errors
+/// Never returns an error.
+///
+pub fn forward(_pid: i32, _sig: Signal) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Returns the events handled by the handler routine of `source`.
+#[inline]
+pub fn signals(source: &Source) -> Vec<Signal> {
+    source.signals.clone()
+}
+
+/// Names of the console control events.
+const EVENT_NAMES: &[(&str, Signal)] = &[
+    ("CTRL_C_EVENT", CTRL_C_EVENT),
+    ("CTRL_BREAK_EVENT", CTRL_BREAK_EVENT),
+    ("CTRL_CLOSE_EVENT", CTRL_CLOSE_EVENT),
+    ("CTRL_LOGOFF_EVENT", CTRL_LOGOFF_EVENT),
+    ("CTRL_SHUTDOWN_EVENT", CTRL_SHUTDOWN_EVENT),
+];
+
+/// Returns the event named `name`, like `CTRL_C_EVENT`, or with the `dwCtrlType` value `name`.
+///
+/// Names are case-insensitive, and the `_EVENT` suffix is optional.
+pub fn signal_from_name(name: &str) -> Option<Signal> {
+    if let Ok(ctrl_type) = name.parse() {
+        return Some(ctrl_type);
+    }
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_suffix("_EVENT").unwrap_or(&name);
+    EVENT_NAMES
+        .iter()
+        .find(|(event, _)| event.strip_suffix("_EVENT") == Some(name))
+        .map(|&(_, ctrl_type)| ctrl_type)
+}
+
+/// Returns the name of `ctrl_type`, like `CTRL_C_EVENT`, or its value for unknown events.
+pub fn signal_name(ctrl_type: Signal) -> String {
+    EVENT_NAMES
+        .iter()
+        .find(|&&(_, event)| event == ctrl_type)
+        .map_or_else(|| ctrl_type.to_string(), |(name, _)| (*name).into())
+}
+
+/// Whether events of the same kind can be merged before the handler routine sees them. Windows
+/// calls the handler routine once per event, on a thread of its own, and every call queues the
+/// event.
+pub const COALESCES_SIGNALS: bool = false;
+
+/// Returns whether `ctrl_type` is one of the console control events.
+pub fn is_supported(ctrl_type: Signal) -> bool {
+    EVENT_NAMES.iter().any(|&(_, event)| event == ctrl_type)
+}
+
+/// Events only sent by a console, none on Windows.
+pub const TERMINAL_SIGNALS: &[Signal] = &[];
+
+/// Returns whether the handler routine has received one of the events it handles since it was
+/// last registered.
+#[inline]
+pub fn routine_reached() -> bool {
+    REACHED.load(Ordering::SeqCst)
+}
+
+/// Returns whether a debugger is attached, from `IsDebuggerPresent()`.
+pub fn debugger_attached() -> Option<bool> {
+    Some(unsafe { IsDebuggerPresent() } != 0)
+}
+
+/// Returns whether the process has a controlling terminal, always `true` on Windows.
+#[inline]
+pub fn has_controlling_terminal() -> bool {
+    true
+}
+
+/// Returns the exit status of the default handler routine, `STATUS_CONTROL_C_EXIT` for every
+/// event.
+#[inline]
+pub fn exit_code(_sig: Signal) -> i32 {
+    STATUS_CONTROL_C_EXIT
+}
+
+/// Exit the process the way the default handler routine does.
+///
+/// Windows has no signal dispositions to reset, the default handler routine exits with
+/// `STATUS_CONTROL_C_EXIT` for every event.
+pub fn re_raise(sig: Signal) -> ! {
+    std::process::exit(exit_code(sig))
+}
+
+/// Remove the handler routine installed by [`init_os_handler()`](fn.init_os_handler.html)
+/// and wake up any thread blocked in [`block_ctrl_c()`](fn.block_ctrl_c.html).
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn deinit_os_handler(source: &Source) -> Result<(), Error> {
+    let result = if SetConsoleCtrlHandler(Some(os_handler), FALSE) == FALSE {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    };
+    if SEMAPHORE == source.semaphore {
+        SEMAPHORE = 0 as HANDLE;
+        HANDLED_EVENT = 0 as HANDLE;
+        READY_EVENT = 0 as HANDLE;
+        HANDLED.store(0, Ordering::SeqCst);
+    }
+
+    source.woken.store(true, Ordering::SeqCst);
+    ReleaseSemaphore(source.semaphore, 1, ptr::null_mut());
+    // Don't keep a handler routine waiting for an event that won't be handled anymore.
+    SetEvent(source.handled);
+    // Make pollers look at the source and notice it is gone.
+    SetEvent(source.ready);
+
+    result
+}
+
+/// Blocks until a Ctrl-C signal is received and returns the received console control event,
+/// e.g. `CTRL_C_EVENT` or `CTRL_BREAK_EVENT`.
+///
+/// Returns `None` if the wait was ended by
+/// [`deinit_os_handler()`](fn.deinit_os_handler.html) instead of a signal.
 ///
 /// Must be called after calling [`init_os_handler()`](fn.init_os_handler.html).
 ///
@@ -65,9 +524,42 @@ pub unsafe fn init_os_handler(_overwrite: bool) -> Result<(), Error> {
 /// Will return an error if a system error occurred.
 ///
 #[inline]
-pub unsafe fn block_ctrl_c() -> Result<(), Error> {
-    match WaitForSingleObject(SEMAPHORE, INFINITE) {
-        WAIT_OBJECT_0 => Ok(()),
+pub unsafe fn block_ctrl_c(source: &Source) -> Result<Option<Signal>, Error> {
+    match WaitForSingleObject(source.semaphore, INFINITE) {
+        WAIT_OBJECT_0 if source.woken.load(Ordering::SeqCst) => Ok(None),
+        WAIT_OBJECT_0 => {
+            let mut events = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+            let event = events.pop_front().unwrap_or(CTRL_C_EVENT);
+            if events.is_empty() {
+                ResetEvent(source.ready);
+            }
+            Ok(Some(event))
+        }
+        WAIT_FAILED => Err(io::Error::last_os_error()),
+        ret => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "WaitForSingleObject(), unexpected return value \"{:x}\"",
+                ret
+            ),
+        )),
+    }
+}
+
+/// Returns whether [`block_ctrl_c()`](fn.block_ctrl_c.html) would return right away.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[inline]
+pub unsafe fn is_pending(source: &Source) -> Result<bool, Error> {
+    match WaitForSingleObject(source.semaphore, 0) {
+        WAIT_OBJECT_0 => {
+            // Put back what we just took, for block_ctrl_c() to take.
+            ReleaseSemaphore(source.semaphore, 1, ptr::null_mut());
+            Ok(true)
+        }
+        WAIT_TIMEOUT => Ok(false),
         WAIT_FAILED => Err(io::Error::last_os_error()),
         ret => Err(io::Error::new(
             io::ErrorKind::Other,
@@ -78,3 +570,31 @@ pub unsafe fn block_ctrl_c() -> Result<(), Error> {
         )),
     }
 }
+
+/// Queue `ctrl_type` for [`block_ctrl_c()`](fn.block_ctrl_c.html) as if the handler routine
+/// had been called for it, without involving the console.
+///
+/// # Errors
+/// Will return an error if a system error occurred.
+///
+#[cfg(feature = "testing")]
+#[inline]
+pub unsafe fn simulate(source: &Source, ctrl_type: Signal) -> Result<(), Error> {
+    let mut events = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    events.push_back(ctrl_type);
+    SetEvent(source.ready);
+    drop(events);
+    if ReleaseSemaphore(source.semaphore, 1, ptr::null_mut()) == FALSE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns a manual-reset event that is signaled while
+/// [`block_ctrl_c()`](fn.block_ctrl_c.html) would return right away.
+///
+/// Waiting for the event doesn't reset it, so it can be waited for alongside other objects.
+#[inline]
+pub fn raw_handle(source: &Source) -> HANDLE {
+    source.ready
+}
@@ -0,0 +1,410 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{
+    lock_init, platform, record_received, register_source, unset_if_current, Error, Signal,
+};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+/// Handler set with [`set_handler_inline()`](fn.set_handler_inline.html).
+struct InlineHandler {
+    source: RawSignalSource,
+    user_handler: Box<dyn FnMut(Signal) -> bool + Send>,
+}
+
+static INLINE: Mutex<Option<InlineHandler>> = Mutex::new(None);
+
+/// Handler set with [`register_inline()`](fn.register_inline.html) on this thread.
+struct LocalHandler {
+    source: RawSignalSource,
+    user_handler: Box<dyn FnMut(Signal) -> bool>,
+}
+
+thread_local! {
+    static LOCAL: RefCell<Option<LocalHandler>> = const { RefCell::new(None) };
+}
+/// Source registered by [`wait_next()`](fn.wait_next.html).
+static NEXT: Mutex<Option<Arc<RawSignalSource>>> = Mutex::new(None);
+
+/// Os handler for Ctrl-C without a signal handling thread, see
+/// [`register_raw()`](fn.register_raw.html).
+///
+/// The handler is unset when this is dropped.
+pub struct RawSignalSource {
+    source: Arc<platform::Source>,
+}
+
+impl RawSignalSource {
+    /// Block until Ctrl-C is received and return the received signal.
+    ///
+    /// # Errors
+    /// Will return an error if a system error occurred. If the handler was unset with
+    /// [`unset_handler()`](fn.unset_handler.html), an [`Error::System`] of kind
+    /// [`Interrupted`](std::io::ErrorKind::Interrupted) is returned.
+    pub fn recv(&self) -> Result<Signal, Error> {
+        match unsafe { platform::block_ctrl_c(&self.source)? } {
+            Some(signal) => {
+                record_received(signal, 1);
+                Ok(signal)
+            }
+            None => Err(Error::System(std::io::ErrorKind::Interrupted.into())),
+        }
+    }
+
+    /// Return the received signal if Ctrl-C has been received, without blocking.
+    ///
+    /// Returns `Ok(None)` if no signal is pending.
+    ///
+    /// # Errors
+    /// See [`recv()`](#method.recv).
+    pub fn try_recv(&self) -> Result<Option<Signal>, Error> {
+        if unsafe { platform::is_pending(&self.source)? } {
+            self.recv().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Return all received signals that are pending, in the order they were received, without
+    /// blocking.
+    ///
+    /// Call this once the file descriptor or event handle of the source became ready, e.g. in
+    /// the readiness callback of an event loop. Returns an empty `Vec` if no signal is pending.
+    ///
+    /// # Errors
+    /// See [`recv()`](#method.recv). An error is only returned if no signal could be read at
+    /// all. If it occurs after some signals were read, those are returned instead, as they are
+    /// gone from the source, and the error is dropped.
+    pub fn take_pending(&self) -> Result<Vec<Signal>, Error> {
+        let mut pending = Vec::new();
+        loop {
+            match self.try_recv() {
+                Ok(Some(signal)) => pending.push(signal),
+                Ok(None) => break,
+                Err(e) if pending.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Returns whether this is still the set handler.
+    fn is_current(&self) -> bool {
+        lock_init()
+            .as_ref()
+            .map_or(false, |current| Arc::ptr_eq(current, &self.source))
+    }
+}
+
+impl IntoIterator for RawSignalSource {
+    type Item = Signal;
+    type IntoIter = SignalIterator;
+
+    fn into_iter(self) -> SignalIterator {
+        SignalIterator { source: self }
+    }
+}
+
+/// Blocking iterator over received signals, see [`iter()`](fn.iter.html).
+///
+/// The handler is unset when this is dropped.
+pub struct SignalIterator {
+    source: RawSignalSource,
+}
+
+impl Iterator for SignalIterator {
+    type Item = Signal;
+
+    /// Block until Ctrl-C is received and return the received signal.
+    ///
+    /// Returns `None` once the handler was unset with [`unset_handler()`](fn.unset_handler.html).
+    /// A system error while waiting is reported on stderr and ends the iteration as well.
+    fn next(&mut self) -> Option<Signal> {
+        match self.source.recv() {
+            Ok(signal) => Some(signal),
+            Err(Error::System(e)) if e.kind() == std::io::ErrorKind::Interrupted => None,
+            Err(e) => {
+                eprintln!("Critical system error while waiting for Ctrl-C: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for RawSignalSource {
+    fn drop(&mut self) {
+        let mut guard = lock_init();
+        if let Err(e) = unset_if_current(&mut guard, &Arc::downgrade(&self.source)) {
+            eprintln!("Error unsetting Ctrl-C handler: {}", e);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::io::AsRawFd for RawSignalSource {
+    /// Returns a file descriptor that is readable whenever a signal is pending.
+    ///
+    /// Only poll the file descriptor for readability, and receive the signals with
+    /// [`take_pending()`](#method.take_pending), [`try_recv()`](#method.try_recv) or
+    /// [`recv()`](#method.recv). Don't read from it.
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        platform::raw_fd(&self.source)
+    }
+}
+
+#[cfg(windows)]
+impl std::os::windows::io::AsRawHandle for RawSignalSource {
+    /// Returns a manual-reset event that is signaled whenever a signal is pending.
+    ///
+    /// Waiting for the event doesn't reset it. Receive the signals with
+    /// [`take_pending()`](#method.take_pending), [`try_recv()`](#method.try_recv) or
+    /// [`recv()`](#method.recv) afterwards.
+    fn as_raw_handle(&self) -> std::os::windows::io::RawHandle {
+        platform::raw_handle(&self.source)
+    }
+}
+
+/// Install the os handler for Ctrl-C without spawning a signal handling thread.
+///
+/// The signals are the same as for [`set_handler()`](fn.set_handler.html), but they are only
+/// queued until received with [`RawSignalSource::recv()`] or [`RawSignalSource::try_recv()`].
+/// The returned source can be integrated into an existing event loop: on Unix it provides a
+/// file descriptor for `poll(2)` or `epoll(7)` through `AsRawFd`, on Windows an event handle for
+/// `WaitForMultipleObjects()` through `AsRawHandle`.
+///
+/// Counts as a set handler, so no other handler can be set while the source lives. Dropping it
+/// unsets the handler.
+///
+/// # Example
+/// ```no_run
+/// let source = ctrlc2::register_raw().expect("Error setting Ctrl-C handler");
+/// loop {
+///     // Poll the source alongside other events...
+///     if let Some(signal) = source.try_recv().expect("Error receiving Ctrl-C") {
+///         println!("Got {:?}! Exiting...", signal);
+///         break;
+///     }
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+pub fn register_raw() -> Result<RawSignalSource, Error> {
+    let source = register_source(platform::DEFAULT_SIGNALS, Default::default())?;
+    Ok(RawSignalSource { source })
+}
+
+/// Install the os handler for Ctrl-C and iterate over the received signals.
+///
+/// Each call to `next()` blocks until a signal is received, on the calling thread, so no
+/// signal handling thread or closure is needed. The iterator ends when the handler is unset
+/// with [`unset_handler()`](fn.unset_handler.html) from another thread, and dropping it unsets the
+/// handler. This is the same as iterating over a source returned by
+/// [`register_raw()`](fn.register_raw.html).
+///
+/// # Example
+/// ```no_run
+/// for signal in ctrlc2::iter().expect("Error setting Ctrl-C handler") {
+///     println!("Got {:?}", signal);
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+pub fn iter() -> Result<SignalIterator, Error> {
+    register_raw().map(IntoIterator::into_iter)
+}
+
+/// Block the calling thread until Ctrl-C is received, keeping the handler set between calls.
+///
+/// Lets e.g. GUI toolkits, which require all work to happen on the main thread, handle the
+/// signals there instead of on a signal handling thread. The first call installs the os
+/// handler like [`register_raw()`](fn.register_raw.html), and later calls keep using it, so
+/// signals received between two calls are queued instead of lost, unlike with
+/// [`wait_for_signal()`](fn.wait_for_signal.html). Counts as a set handler until unset with
+/// [`unset_handler()`](fn.unset_handler.html). The next call after that installs the os
+/// handler again.
+///
+/// # Example
+/// ```no_run
+/// loop {
+///     let signal = ctrlc2::wait_next().expect("Error waiting for Ctrl-C");
+///     println!("Got {:?}, shutting down on the calling thread", signal);
+///     break;
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if another handler is set, or an error if a system
+/// error occurred. If the wait is ended by [`unset_handler()`](fn.unset_handler.html) from
+/// another thread, an [`Error::System`] of kind
+/// [`Interrupted`](std::io::ErrorKind::Interrupted) is returned.
+pub fn wait_next() -> Result<Signal, Error> {
+    let source = {
+        let mut next = NEXT.lock().unwrap_or_else(|e| e.into_inner());
+        match next.as_ref() {
+            Some(source) if source.is_current() => Arc::clone(source),
+            _ => {
+                let source = Arc::new(register_raw()?);
+                *next = Some(Arc::clone(&source));
+                source
+            }
+        }
+    };
+    source.recv()
+}
+
+/// Register signal handler for Ctrl-C that runs from [`poll_signal()`](fn.poll_signal.html).
+///
+/// No signal handling thread is spawned, which suits single-threaded and constrained
+/// environments. Received signals are queued until the program calls `poll_signal()` from its
+/// own loop, which then runs `user_handler` on the calling thread. Like with
+/// [`set_handler_with_signal()`](fn.set_handler_with_signal.html), the handler is told the
+/// received signal, and returning `true` unsets it.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_inline(|_| true).expect("Error setting Ctrl-C handler");
+/// while ctrlc2::is_handler_set() {
+///     // Do some work...
+///     ctrlc2::poll_signal().expect("Error receiving Ctrl-C");
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+pub fn set_handler_inline<F>(user_handler: F) -> Result<(), Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    let source = register_raw()?;
+    let previous = INLINE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(InlineHandler {
+            source,
+            user_handler: Box::new(user_handler),
+        });
+    // A previous handler was already unset, dropping it outside of the lock is enough.
+    drop(previous);
+    Ok(())
+}
+
+/// Run the handler set with [`set_handler_inline()`](fn.set_handler_inline.html) if Ctrl-C has
+/// been received, without blocking.
+///
+/// Returns the signal the handler was run for, or `Ok(None)` if no signal is pending or no
+/// inline handler is set. Must not be called from the handler itself.
+///
+/// # Errors
+/// Will return an error if a system error occurred while receiving the signal.
+pub fn poll_signal() -> Result<Option<Signal>, Error> {
+    let mut guard = INLINE.lock().unwrap_or_else(|e| e.into_inner());
+    let inline = match guard.as_mut() {
+        Some(inline) => inline,
+        None => return Ok(None),
+    };
+    match inline.source.try_recv() {
+        Ok(Some(signal)) => {
+            if (inline.user_handler)(signal) {
+                guard.take();
+            }
+            Ok(Some(signal))
+        }
+        Ok(None) => Ok(None),
+        // Unset with unset_handler() in the meantime.
+        Err(Error::System(e)) if e.kind() == std::io::ErrorKind::Interrupted => {
+            guard.take();
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Register signal handler for Ctrl-C that runs on the calling thread from
+/// [`pump()`](fn.pump.html).
+///
+/// Works like [`set_handler_inline()`](fn.set_handler_inline.html), but `user_handler` is kept
+/// on the calling thread instead of being shared with others, so it doesn't need to be `Send`
+/// and can use e.g. `Rc` or `RefCell` state. Only `pump()` on the same thread runs it. The
+/// handler is unset when it returns `true`, and when the calling thread exits.
+///
+/// # Example
+/// ```no_run
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// let stop = Rc::new(Cell::new(false));
+/// let handler_stop = Rc::clone(&stop);
+/// ctrlc2::register_inline(move |_| {
+///     handler_stop.set(true);
+///     true
+/// })
+/// .expect("Error setting Ctrl-C handler");
+/// while !stop.get() {
+///     // Do some work...
+///     ctrlc2::pump().expect("Error receiving Ctrl-C");
+/// }
+/// ```
+///
+/// # Errors
+/// Will return [`Error::MultipleHandlers`] if a handler is already set, or an error if a system
+/// error occurred while setting the handler.
+pub fn register_inline<F>(user_handler: F) -> Result<(), Error>
+where
+    F: FnMut(Signal) -> bool + 'static,
+{
+    let source = register_raw()?;
+    let previous = LOCAL.with(|local| {
+        local.borrow_mut().replace(LocalHandler {
+            source,
+            user_handler: Box::new(user_handler),
+        })
+    });
+    // A previous handler was already unset, dropping it outside of the borrow is enough.
+    drop(previous);
+    Ok(())
+}
+
+/// Run the handler set with [`register_inline()`](fn.register_inline.html) on this thread for
+/// each pending signal, without blocking.
+///
+/// Returns the number of signals the handler was run for, which is `0` if no signal is pending
+/// or no handler is set on this thread. Stops early once the handler returned `true`. Must not be
+/// called from the handler itself.
+///
+/// # Errors
+/// Will return an error if a system error occurred while receiving the signals.
+pub fn pump() -> Result<usize, Error> {
+    LOCAL.with(|local| {
+        let mut guard = local.borrow_mut();
+        let mut handled = 0;
+        while let Some(handler) = guard.as_mut() {
+            match handler.source.try_recv() {
+                Ok(Some(signal)) => {
+                    handled += 1;
+                    if (handler.user_handler)(signal) {
+                        guard.take();
+                    }
+                }
+                Ok(None) => break,
+                // Unset with unset_handler() in the meantime.
+                Err(Error::System(e)) if e.kind() == std::io::ErrorKind::Interrupted => {
+                    guard.take();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(handled)
+    })
+}
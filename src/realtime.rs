@@ -0,0 +1,128 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{Error, HandlerConfig};
+use nix::errno::Errno;
+use nix::libc;
+use std::thread::JoinHandle;
+
+/// Returns the number of the lowest real-time signal, `SIGRTMIN`.
+pub fn sigrtmin() -> libc::c_int {
+    libc::SIGRTMIN()
+}
+
+/// Returns the number of the highest real-time signal, `SIGRTMAX`.
+pub fn sigrtmax() -> libc::c_int {
+    libc::SIGRTMAX()
+}
+
+/// Signal mask of the given real-time signals.
+struct RealtimeSet(libc::sigset_t);
+
+impl RealtimeSet {
+    fn new(signals: &[libc::c_int]) -> Result<Self, Error> {
+        unsafe {
+            let mut set = std::mem::zeroed();
+            libc::sigemptyset(&mut set);
+            for &signal in signals {
+                if !(sigrtmin()..=sigrtmax()).contains(&signal) {
+                    return Err(Errno::EINVAL.into());
+                }
+                libc::sigaddset(&mut set, signal);
+            }
+            Ok(RealtimeSet(set))
+        }
+    }
+
+    /// Change the signal mask of the calling thread, returning the previous one.
+    fn thread_mask(&self, how: libc::c_int) -> Result<RealtimeSet, Error> {
+        unsafe {
+            let mut old = std::mem::zeroed();
+            match libc::pthread_sigmask(how, &self.0, &mut old) {
+                0 => Ok(RealtimeSet(old)),
+                e => Err(Errno::from_raw(e).into()),
+            }
+        }
+    }
+
+    /// Wait for one of the signals with `sigwaitinfo(2)`, retrying on `EINTR`.
+    fn wait(&self) -> Result<libc::c_int, Errno> {
+        loop {
+            match unsafe { libc::sigwaitinfo(&self.0, std::ptr::null_mut()) } {
+                -1 if Errno::last() == Errno::EINTR => {}
+                -1 => return Err(Errno::last()),
+                signal => return Ok(signal),
+            }
+        }
+    }
+}
+
+/// Register signal handler for the given real-time signals, `SIGRTMIN..=SIGRTMAX`.
+///
+/// Real-time signals can't be represented by [`Signal`](crate::Signal), so they are passed
+/// as raw signal numbers, computed from [`sigrtmin()`], and `user_handler` is told the number of
+/// the received signal. They are received like with
+/// [`set_handler_sigwait()`](fn.set_handler_sigwait.html): blocked on the calling thread, and
+/// waited for on the spawned signal handling thread, so the same requirements apply. Call
+/// this from the main thread before spawning other threads, which inherit the signal mask.
+/// `user_handler` is run for each signal until it returns `true`, and the signals stay blocked
+/// afterwards.
+///
+/// Unlike standard signals, real-time signals are queued instead of coalesced: `user_handler`
+/// runs once for every signal sent. Pending real-time signals are received lowest number first,
+/// and instances of the same signal in the order they were sent.
+///
+/// Only available on Linux and Android.
+///
+/// # Example
+/// ```no_run
+/// let reload = ctrlc2::sigrtmin() + 1;
+/// ctrlc2::set_handler_realtime(&[reload], |signal| {
+///     println!("Got real-time signal {}", signal);
+///     false
+/// })
+/// .expect("Error setting signal handler");
+/// ```
+///
+/// # Errors
+/// Will return [`Error::NoSignals`] if `signals` is empty, an error of kind `EINVAL` if one of
+/// them is not a real-time signal, or an error if a system error occurred while blocking the
+/// signals or spawning the signal handling thread.
+pub fn set_handler_realtime<F>(
+    signals: &[libc::c_int],
+    mut user_handler: F,
+) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(libc::c_int) -> bool + 'static + Send,
+{
+    if signals.is_empty() {
+        return Err(Error::NoSignals);
+    }
+    let set = RealtimeSet::new(signals)?;
+
+    let old_mask = set.thread_mask(libc::SIG_BLOCK)?;
+    let spawned = HandlerConfig::default()
+        .thread_builder()
+        .spawn(move || loop {
+            let signal = match set.wait() {
+                Ok(signal) => signal,
+                Err(e) => {
+                    eprintln!("Critical system error while waiting for signal: {}", e);
+                    break;
+                }
+            };
+            if user_handler(signal) {
+                break;
+            }
+        });
+    spawned.map_err(|e| {
+        let _ = old_mask.thread_mask(libc::SIG_SETMASK);
+        Error::System(e)
+    })
+}
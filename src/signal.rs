@@ -7,7 +7,7 @@
 // notice may not be copied, modified, or distributed except
 // according to those terms.
 
-use crate::platform;
+use crate::{platform, Error};
 
 /// A cross-platform way to represent Ctrl-C or program termination signal. Other
 /// signals/events are supported via `Other`-variant.
@@ -21,3 +21,99 @@ pub enum SignalType {
     /// Other signal/event using platform-specific data
     Other(platform::Signal),
 }
+
+/// Access to the platform-specific number of a [`Signal`](crate::Signal).
+///
+/// `Signal` is `nix::sys::signal::Signal` on Unix and the `dwCtrlType` value of the console
+/// control event on Windows, so the accessor is provided by this trait instead of an inherent
+/// method.
+///
+/// # Example
+/// ```no_run
+/// use ctrlc2::AsRawSignal;
+///
+/// let signal = ctrlc2::wait_for_signal().expect("Error waiting for Ctrl-C");
+/// println!("Received signal {}", signal.as_raw());
+/// ```
+pub trait AsRawSignal {
+    /// Returns the signal number, e.g. for logging or for passing to `libc::raise`.
+    fn as_raw(&self) -> i32;
+}
+
+impl AsRawSignal for platform::Signal {
+    #[allow(clippy::unnecessary_cast)]
+    fn as_raw(&self) -> i32 {
+        *self as i32
+    }
+}
+
+/// Returns the signals handled by [`set_handler()`](crate::set_handler) on the current platform.
+///
+/// On Unix these are `SIGINT`, plus `SIGTERM` and `SIGHUP` with the `termination` feature and
+/// `SIGQUIT` with the `quit` feature. On Windows these are `CTRL_C_EVENT` and
+/// `CTRL_BREAK_EVENT`, plus `CTRL_CLOSE_EVENT`, `CTRL_LOGOFF_EVENT` and `CTRL_SHUTDOWN_EVENT`
+/// with the `termination` feature.
+///
+/// # Example
+/// ```
+/// use ctrlc2::AsRawSignal;
+///
+/// for signal in ctrlc2::all_signals() {
+///     println!("Handling {:?} ({})", signal, signal.as_raw());
+/// }
+/// ```
+pub fn all_signals() -> &'static [platform::Signal] {
+    platform::DEFAULT_SIGNALS
+}
+
+/// Parse a signal from its name or number, e.g. from a configuration file.
+///
+/// `Signal` is a foreign type on every platform, so this and
+/// [`signal_name()`](fn.signal_name.html) take the place of `FromStr` and `Display`. On Unix,
+/// names like `SIGTERM` are accepted, with or without the `SIG` prefix. On Windows, the names
+/// of the console control events like `CTRL_C_EVENT` are accepted, with or without the
+/// `_EVENT` suffix. Names are case-insensitive, and surrounding whitespace is ignored.
+///
+/// # Example
+/// ```
+/// # #[cfg(unix)]
+/// assert_eq!(ctrlc2::parse_signal("sigterm").unwrap(), ctrlc2::Signal::SIGTERM);
+/// # #[cfg(unix)]
+/// assert_eq!(ctrlc2::parse_signal("2").unwrap(), ctrlc2::Signal::SIGINT);
+/// ```
+///
+/// # Errors
+/// Will return [`Error::UnknownSignal`] with `name` if it names no signal of the platform.
+pub fn parse_signal(name: &str) -> Result<platform::Signal, Error> {
+    let name = name.trim();
+    platform::signal_from_name(name).ok_or_else(|| Error::UnknownSignal(name.into()))
+}
+
+/// Parse a comma-separated list of signals, like `"SIGTERM,SIGHUP"`.
+///
+/// Each entry is parsed with [`parse_signal()`](fn.parse_signal.html), and empty entries are
+/// skipped. The result can be passed to [`set_handler_for()`](crate::set_handler_for).
+///
+/// # Example
+/// ```no_run
+/// let signals = ctrlc2::parse_signal_list("SIGTERM, SIGHUP").expect("Invalid signal list");
+/// ctrlc2::set_handler_for(&signals, |_| true).expect("Error setting signal handler");
+/// ```
+///
+/// # Errors
+/// Will return [`Error::UnknownSignal`] with the first entry that names no signal.
+pub fn parse_signal_list(list: &str) -> Result<Vec<platform::Signal>, Error> {
+    list.split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(parse_signal)
+        .collect()
+}
+
+/// Returns the canonical name of `signal`, which [`parse_signal()`](fn.parse_signal.html) parses
+/// back.
+///
+/// On Unix this is e.g. `SIGINT`, the same as the `Display` implementation of the nix type. On
+/// Windows it is e.g. `CTRL_C_EVENT`, or the number of events without a name.
+pub fn signal_name(signal: platform::Signal) -> String {
+    platform::signal_name(signal)
+}
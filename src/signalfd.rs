@@ -0,0 +1,126 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{platform, record_received, Error, Signal};
+use nix::errno::Errno;
+use nix::sys::signal::{SigSet, SigmaskHow};
+use nix::sys::signalfd::{self, SfdFlags};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// Ctrl-C received through a Linux `signalfd`, see
+/// [`register_signalfd()`](fn.register_signalfd.html).
+///
+/// The signals are unblocked on the calling thread again when this is dropped.
+pub struct SignalFd {
+    fd: signalfd::SignalFd,
+    old_mask: SigSet,
+}
+
+impl SignalFd {
+    /// Block until Ctrl-C is received and return the received signal.
+    ///
+    /// # Errors
+    /// Will return an error if a system error occurred.
+    pub fn recv(&self) -> Result<Signal, Error> {
+        loop {
+            if let Some(signal) = self.try_recv()? {
+                return Ok(signal);
+            }
+
+            let mut fd = nix::libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: nix::libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { nix::libc::poll(&mut fd, 1, -1) } < 0 && Errno::last() != Errno::EINTR {
+                return Err(Errno::last().into());
+            }
+        }
+    }
+
+    /// Return the received signal if Ctrl-C has been received, without blocking.
+    ///
+    /// Returns `Ok(None)` if no signal is pending.
+    ///
+    /// # Errors
+    /// Will return an error if a system error occurred.
+    pub fn try_recv(&self) -> Result<Option<Signal>, Error> {
+        loop {
+            match self.fd.read_signal() {
+                Ok(Some(info)) => {
+                    let signal = Signal::try_from(info.ssi_signo as nix::libc::c_int)?;
+                    record_received(signal, 1);
+                    return Ok(Some(signal));
+                }
+                Ok(None) => return Ok(None),
+                Err(Errno::EINTR) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for SignalFd {
+    fn drop(&mut self) {
+        if let Err(e) = self.old_mask.thread_set_mask() {
+            eprintln!("Error restoring the signal mask: {}", e);
+        }
+    }
+}
+
+impl AsRawFd for SignalFd {
+    /// Returns the `signalfd`, which is readable whenever a signal is pending.
+    ///
+    /// Poll the file descriptor for readability, e.g. with `epoll(7)`, and receive the signal
+    /// with [`try_recv()`](#method.try_recv) or [`recv()`](#method.recv).
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// Receive Ctrl-C through a Linux `signalfd` instead of a signal handler.
+///
+/// The signals are the same as for [`set_handler()`](fn.set_handler.html). They are blocked on
+/// the calling thread and read from a `signalfd(2)`, so no code runs in signal context at all,
+/// and no signal handling thread is needed. The returned [`SignalFd`] can be waited on directly
+/// or integrated into an `epoll(7)` loop.
+///
+/// Signals are only routed to the `signalfd` while they are blocked on every thread, otherwise
+/// the process receives them as usual. Call this from the main thread before spawning other
+/// threads, which inherit the signal mask, and don't combine it with the other handlers of this
+/// crate, which never see the blocked signals. Child processes inherit the signal mask as well,
+/// so unblock the signals in them before `execve(2)` if needed.
+///
+/// Only available on Linux with the `signalfd` feature.
+///
+/// # Example
+/// ```no_run
+/// let signals = ctrlc2::register_signalfd().expect("Error setting up signalfd");
+/// let signal = signals.recv().expect("Error receiving Ctrl-C");
+/// println!("Got {:?}! Exiting...", signal);
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while blocking the signals or creating the
+/// `signalfd`.
+pub fn register_signalfd() -> Result<SignalFd, Error> {
+    let mut mask = SigSet::empty();
+    for &signal in platform::DEFAULT_SIGNALS {
+        mask.add(signal);
+    }
+
+    let old_mask = mask.thread_swap_mask(SigmaskHow::SIG_BLOCK)?;
+    match signalfd::SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC) {
+        Ok(fd) => Ok(SignalFd { fd, old_mask }),
+        Err(e) => {
+            let _ = old_mask.thread_set_mask();
+            Err(e.into())
+        }
+    }
+}
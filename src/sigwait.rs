@@ -0,0 +1,76 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{platform, record_received, Error, HandlerConfig, Signal};
+use nix::errno::Errno;
+use nix::sys::signal::{SigSet, SigmaskHow};
+use std::thread::JoinHandle;
+
+/// Register signal handler for Ctrl-C that receives the signals with `sigwait(3)`.
+///
+/// The signals are the same as for [`set_handler()`](fn.set_handler.html). Instead of
+/// installing a signal handler, they are blocked on the calling thread with
+/// `pthread_sigmask(3)`, and the spawned signal handling thread waits for them with `sigwait`. No
+/// code runs in signal context at all, so there are no async-signal-safety pitfalls. As with
+/// `set_handler()`, `user_handler` is run for each signal until it returns `true`.
+///
+/// Signals are only received by `sigwait` while they are blocked on every thread, otherwise the
+/// process receives them as usual. Call this from the main thread before spawning other threads,
+/// which inherit the signal mask, and don't combine it with the other handlers of this crate,
+/// which never see the blocked signals. The signals stay blocked after the handler has returned
+/// `true`, so later signals are left pending. Child processes inherit the signal mask as well, so
+/// unblock the signals in them before `execve(2)` if needed.
+///
+/// Only available on Unix with the `sigwait` feature.
+///
+/// # Example
+/// ```no_run
+/// ctrlc2::set_handler_sigwait(|signal| {
+///     println!("Got {:?}! Exiting...", signal);
+///     true
+/// })
+/// .expect("Error setting Ctrl-C handler");
+///
+/// // Spawn the other threads of the program only now, so they block the signals as well.
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while blocking the signals or spawning the
+/// signal handling thread.
+pub fn set_handler_sigwait<F>(mut user_handler: F) -> Result<JoinHandle<()>, Error>
+where
+    F: FnMut(Signal) -> bool + 'static + Send,
+{
+    let mut mask = SigSet::empty();
+    for &signal in platform::DEFAULT_SIGNALS {
+        mask.add(signal);
+    }
+
+    let old_mask = mask.thread_swap_mask(SigmaskHow::SIG_BLOCK)?;
+    let spawned = HandlerConfig::default()
+        .thread_builder()
+        .spawn(move || loop {
+            let signal = match mask.wait() {
+                Ok(signal) => signal,
+                Err(Errno::EINTR) => continue,
+                Err(e) => {
+                    eprintln!("Critical system error while waiting for Ctrl-C: {}", e);
+                    break;
+                }
+            };
+            record_received(signal, 1);
+            if user_handler(signal) {
+                break;
+            }
+        });
+    spawned.map_err(|e| {
+        let _ = old_mask.thread_set_mask();
+        Error::System(e)
+    })
+}
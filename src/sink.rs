@@ -0,0 +1,81 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{init_and_set_handler, platform, Error, Signal};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// Destination for the signals forwarded by
+/// [`set_handler_sender()`](fn.set_handler_sender.html).
+///
+/// Implemented for the senders of [`std::sync::mpsc`], and for the senders of
+/// `crossbeam-channel` and `flume` if the `crossbeam` and `flume` features are enabled.
+pub trait SignalSink: Send + 'static {
+    /// Forward `signal` to the receiving side.
+    ///
+    /// Returns `false` if the receiving side is gone, which stops the signal handling thread.
+    fn send_signal(&mut self, signal: Signal) -> bool;
+}
+
+impl SignalSink for mpsc::Sender<Signal> {
+    fn send_signal(&mut self, signal: Signal) -> bool {
+        self.send(signal).is_ok()
+    }
+}
+
+impl SignalSink for mpsc::SyncSender<Signal> {
+    fn send_signal(&mut self, signal: Signal) -> bool {
+        self.send(signal).is_ok()
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+impl SignalSink for crossbeam_channel::Sender<Signal> {
+    fn send_signal(&mut self, signal: Signal) -> bool {
+        self.send(signal).is_ok()
+    }
+}
+
+#[cfg(feature = "flume")]
+impl SignalSink for flume::Sender<Signal> {
+    fn send_signal(&mut self, signal: Signal) -> bool {
+        self.send(signal).is_ok()
+    }
+}
+
+/// Register a signal handler for Ctrl-C that forwards each received signal into `sink`.
+///
+/// Works like [`channel()`](fn.channel.html), but the signals are sent into a channel provided
+/// by the caller, so they can take part in an existing `select!` loop without an extra
+/// forwarding hop. Sending blocks the signal handling thread if the channel is bounded and
+/// full. The signal handling thread stops once the receiving side has been dropped and the
+/// next signal arrives.
+///
+/// # Example
+/// ```no_run
+/// let (tx, rx) = std::sync::mpsc::sync_channel(1);
+/// ctrlc2::set_handler_sender(tx).expect("Error setting Ctrl-C handler");
+///
+/// println!("Waiting for Ctrl-C...");
+/// let signal = rx.recv().expect("Could not receive from channel.");
+/// println!("Got {:?}! Exiting...", signal);
+/// ```
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn set_handler_sender<S>(mut sink: S) -> Result<JoinHandle<()>, Error>
+where
+    S: SignalSink,
+{
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |signal| !sink.send_signal(signal),
+        Default::default(),
+    )
+}
@@ -0,0 +1,32 @@
+// Copyright (c) 2017 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+use crate::{init_and_set_handler, platform, Error, Signal};
+
+/// Register a signal handler for Ctrl-C that yields each received signal from a stream.
+///
+/// The signal handling thread pushes every signal into the returned
+/// [`Stream`](futures_core::Stream), which makes it easy to consume repeated signals with
+/// stream combinators, unlike [`set_async_handler()`](fn.set_async_handler.html). The stream
+/// ends when the handler is unset with [`unset_handler()`](fn.unset_handler.html), and the
+/// signal handling thread stops on the next signal after the stream has been dropped.
+///
+/// The stream is runtime-agnostic and requires the `stream` feature.
+///
+/// # Errors
+/// Will return an error if a system error occurred while setting the handler.
+pub fn signal_stream() -> Result<impl futures_core::Stream<Item = Signal>, Error> {
+    let (tx, rx) = futures_channel::mpsc::unbounded();
+    init_and_set_handler(
+        platform::DEFAULT_SIGNALS,
+        move |signal| tx.unbounded_send(signal).is_err(),
+        Default::default(),
+    )?;
+    Ok(rx)
+}
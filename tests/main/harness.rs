@@ -42,8 +42,8 @@ pub mod platform {
     };
     use windows_sys::Win32::System::Console::{
         AllocConsole, AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, GetConsoleMode,
-        GetStdHandle, SetStdHandle, ATTACH_PARENT_PROCESS, CTRL_C_EVENT, STD_ERROR_HANDLE,
-        STD_OUTPUT_HANDLE,
+        GetStdHandle, SetStdHandle, ATTACH_PARENT_PROCESS, CTRL_BREAK_EVENT, CTRL_C_EVENT,
+        STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
     };
 
     /// Stores a piped stdout handle or a cache that gets
@@ -202,6 +202,11 @@ pub mod platform {
         assert!(GenerateConsoleCtrlEvent(CTRL_C_EVENT, 0) != 0);
     }
 
+    #[allow(dead_code)]
+    pub unsafe fn raise_ctrl_break() {
+        assert!(GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, 0) != 0);
+    }
+
     /// Print to both consoles, this is not thread safe.
     pub unsafe fn print(fmt: ::std::fmt::Arguments) {
         use self::io::Write;
@@ -241,9 +246,9 @@ pub fn run_harness(f: fn()) {
         (default)(info);
     }));
 
-    println!("");
+    println!();
     f();
-    println!("");
+    println!();
 
     unsafe {
         platform::cleanup().unwrap();
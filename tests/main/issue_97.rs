@@ -22,9 +22,147 @@ fn expect_multiple_handlers() {
     }
 }
 
+fn test_try_set_handler_for() {
+    #[cfg(unix)]
+    {
+        use ctrlc2::Signal;
+
+        // signal_hook owns SIGINT, which must not keep us from handling SIGTERM.
+        match ctrlc2::try_set_handler_for(&[Signal::SIGTERM, Signal::SIGINT], |_| true) {
+            Err(ctrlc2::Error::HandlerExists(Signal::SIGINT)) => {}
+            ret => panic!("{:?}", ret),
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = ctrlc2::try_set_handler_for(&[Signal::SIGTERM], move |signal| {
+            tx.send(signal).unwrap();
+            true
+        })
+        .unwrap();
+
+        nix::sys::signal::raise(Signal::SIGTERM).unwrap();
+
+        handle.join().unwrap();
+        assert_eq!(rx.recv().unwrap(), Signal::SIGTERM);
+        ctrlc2::unset_handler().unwrap();
+    }
+}
+
+fn test_restore() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let hook = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&hook)).unwrap();
+
+    let (handle, previous) = ctrlc2::set_handler_saving_previous(|| true).unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+
+    ctrlc2::restore(previous).unwrap();
+    hook.store(false, Ordering::SeqCst);
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(hook.load(Ordering::SeqCst));
+}
+
+fn test_set_handler_chained() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    ctrlc2::unset_handler().unwrap();
+
+    let hook = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&hook)).unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    ctrlc2::set_handler_chained(move |_| {
+        tx.send(()).unwrap();
+        ctrlc2::Chain::Propagate
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    rx.recv().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(hook.load(Ordering::SeqCst));
+
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(all(unix, feature = "signal-hook"))]
+fn test_builder_signal_hook() {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    ctrlc2::unset_handler().unwrap();
+
+    let hook = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&hook)).unwrap();
+
+    // signal_hook owns SIGINT, which is no conflict when registering through it.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::Builder::new()
+        .signals(&[ctrlc2::Signal::SIGINT])
+        .signal_hook(true)
+        .build(move |signal| {
+            tx.send(signal).unwrap();
+            false
+        })
+        .unwrap();
+    assert_eq!(
+        ctrlc2::installed_signals(),
+        Some(vec![ctrlc2::Signal::SIGINT])
+    );
+    assert!(matches!(
+        ctrlc2::try_set_handler(|| true),
+        Err(ctrlc2::Error::MultipleHandlers)
+    ));
+
+    // Handlers registered with signal_hook later keep running as well.
+    let later = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&later)).unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(rx.recv().unwrap(), ctrlc2::Signal::SIGINT);
+    assert!(hook.load(Ordering::SeqCst));
+    assert!(later.load(Ordering::SeqCst));
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+
+    // Only our action is unregistered.
+    hook.store(false, Ordering::SeqCst);
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(hook.load(Ordering::SeqCst));
+    assert!(rx.try_recv().is_err());
+}
+
 fn tests() {
     run_tests!(run_signal_hook);
     run_tests!(expect_multiple_handlers);
+    run_tests!(test_try_set_handler_for);
+    run_tests!(test_restore);
+    run_tests!(test_set_handler_chained);
+    #[cfg(all(unix, feature = "signal-hook"))]
+    run_tests!(test_builder_signal_hook);
 }
 
 fn main() {
@@ -38,10 +38,1909 @@ fn test_set_handler() {
     }
 }
 
+fn test_set_handler_with_signal() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_signal(move |signal| {
+        tx.send(signal).unwrap();
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    let signal = rx.recv().unwrap();
+    #[cfg(unix)]
+    {
+        assert_eq!(signal, ctrlc2::Signal::SIGINT);
+        assert_eq!(ctrlc2::AsRawSignal::as_raw(&signal), nix::libc::SIGINT);
+    }
+    #[cfg(windows)]
+    {
+        assert_eq!(signal, windows_sys::Win32::System::Console::CTRL_C_EVENT);
+        assert_eq!(ctrlc2::AsRawSignal::as_raw(&signal), 0);
+    }
+}
+
+fn test_all_signals() {
+    let signals = ctrlc2::all_signals();
+    #[cfg(unix)]
+    {
+        assert!(signals.contains(&ctrlc2::Signal::SIGINT));
+        #[cfg(feature = "termination")]
+        assert!(signals.contains(&ctrlc2::Signal::SIGTERM));
+    }
+    #[cfg(windows)]
+    assert!(signals.contains(&windows_sys::Win32::System::Console::CTRL_C_EVENT));
+}
+
+fn test_error_source() {
+    use std::error::Error as _;
+
+    let e = ctrlc2::Error::System(std::io::Error::new(std::io::ErrorKind::Other, "os failure"));
+    assert_eq!(
+        e.to_string(),
+        "Ctrl-C error: Unexpected system error: os failure"
+    );
+    assert_eq!(e.source().unwrap().to_string(), "os failure");
+
+    let e = ctrlc2::Error::MultipleHandlers;
+    assert_eq!(
+        e.to_string(),
+        "Ctrl-C error: Ctrl-C signal handler already registered"
+    );
+    assert!(e.source().is_none());
+}
+
+fn test_set_handler_unsupported() {
+    #[cfg(unix)]
+    let signal = ctrlc2::Signal::SIGKILL;
+    #[cfg(windows)]
+    let signal = 42;
+
+    match ctrlc2::set_handler_for(&[signal], |_| true) {
+        Err(e @ ctrlc2::Error::Unsupported(_)) => {
+            assert!(matches!(e, ctrlc2::Error::Unsupported(s) if s == signal));
+            assert!(e.to_string().ends_with(&ctrlc2::signal_name(signal)));
+        }
+        other => panic!("unexpected result: {:?}", other.map(drop)),
+    }
+}
+
+fn test_handler_reachability_check() {
+    let status = ctrlc2::handler_reachability_check();
+    #[cfg(any(target_os = "linux", windows))]
+    assert_ne!(status, ctrlc2::ReachabilityStatus::Unknown);
+    assert_eq!(status, ctrlc2::handler_reachability_check());
+}
+
+#[cfg(windows)]
+fn test_windows_handler_reachable() {
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(ctrlc2::windows_handler_reachable(), Some(false));
+
+    let handle = ctrlc2::set_handler(|| true).unwrap();
+    assert_eq!(ctrlc2::windows_handler_reachable(), None);
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    assert_eq!(ctrlc2::windows_handler_reachable(), Some(true));
+
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(ctrlc2::windows_handler_reachable(), Some(false));
+}
+
+fn test_parse_signal() {
+    for &signal in ctrlc2::all_signals() {
+        let name = ctrlc2::signal_name(signal);
+        assert_eq!(ctrlc2::parse_signal(&name).unwrap(), signal);
+        assert_eq!(ctrlc2::parse_signal(&name.to_lowercase()).unwrap(), signal);
+    }
+    #[cfg(unix)]
+    {
+        use ctrlc2::Signal;
+        assert_eq!(ctrlc2::parse_signal("TERM").unwrap(), Signal::SIGTERM);
+        assert_eq!(ctrlc2::parse_signal(" 1 ").unwrap(), Signal::SIGHUP);
+        assert_eq!(
+            ctrlc2::parse_signal_list("SIGTERM, sighup,").unwrap(),
+            [Signal::SIGTERM, Signal::SIGHUP]
+        );
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Console::{CTRL_BREAK_EVENT, CTRL_C_EVENT};
+        assert_eq!(
+            ctrlc2::parse_signal("ctrl_break").unwrap(),
+            CTRL_BREAK_EVENT
+        );
+        assert_eq!(
+            ctrlc2::parse_signal_list("CTRL_C_EVENT,1").unwrap(),
+            [CTRL_C_EVENT, CTRL_BREAK_EVENT]
+        );
+    }
+    assert!(ctrlc2::parse_signal_list("").unwrap().is_empty());
+    match ctrlc2::parse_signal_list("SIGINT,NOPE") {
+        Err(ctrlc2::Error::UnknownSignal(name)) => assert_eq!(name, "NOPE"),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+fn test_set_handler_flow() {
+    use std::ops::ControlFlow;
+
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut count = 0;
+    let handle = ctrlc2::set_handler_flow(move || {
+        count += 1;
+        tx.send(()).unwrap();
+        if count < 2 {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    })
+    .unwrap();
+
+    // The first signal keeps the thread going, the second one stops it.
+    for _ in 0..2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        rx.recv().unwrap();
+    }
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_persistent() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_persistent(move || tx.send(()).unwrap()).unwrap();
+    for _ in 0..3 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        rx.recv().unwrap();
+    }
+    assert!(!handle.is_finished());
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+}
+
+fn test_set_handler_idempotent() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let first = ctrlc2::set_handler_idempotent(move || {
+        tx.send(()).unwrap();
+        true
+    })
+    .unwrap();
+    let second = ctrlc2::set_handler_idempotent(|| panic!("handler should not run")).unwrap();
+    assert_eq!(first.thread(), second.thread());
+    assert!(matches!(
+        ctrlc2::set_handler(|| true),
+        Err(ctrlc2::Error::MultipleHandlers)
+    ));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    let waiter = std::thread::spawn(move || second.join().unwrap());
+    first.join().unwrap();
+    waiter.join().unwrap();
+    assert!(first.is_finished());
+    rx.recv().unwrap();
+
+    // A new handler is set once the previous one was unset.
+    ctrlc2::unset_handler().unwrap();
+    let third = ctrlc2::set_handler_idempotent(|| true).unwrap();
+    assert_ne!(third.thread(), first.thread());
+    ctrlc2::unset_handler().unwrap();
+    third.join().unwrap();
+}
+
+fn test_set_handler_with_state() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_state(0, move |count: &mut i32| {
+        *count += 1;
+        tx.send(*count).unwrap();
+        *count == 2
+    })
+    .unwrap();
+
+    for expected in 1..=2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        assert_eq!(rx.recv().unwrap(), expected);
+    }
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_current_disposition() {
+    ctrlc2::unset_handler().unwrap();
+
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet};
+
+        extern "C" fn custom(_: nix::libc::c_int) {}
+
+        let signal = ctrlc2::Signal::SIGUSR1;
+        let handle = ctrlc2::set_handler_for(&[signal], |_| true).unwrap();
+        assert_eq!(
+            ctrlc2::current_disposition(signal).unwrap(),
+            ctrlc2::Disposition::Own
+        );
+
+        // Something else replaces our handler.
+        let action = SigAction::new(
+            SigHandler::Handler(custom),
+            SaFlags::empty(),
+            SigSet::empty(),
+        );
+        let ours = unsafe { sigaction(signal, &action).unwrap() };
+        assert_eq!(
+            ctrlc2::current_disposition(signal).unwrap(),
+            ctrlc2::Disposition::Custom
+        );
+        unsafe { sigaction(signal, &ours).unwrap() };
+
+        ctrlc2::unset_handler().unwrap();
+        handle.join().unwrap();
+        assert_eq!(
+            ctrlc2::current_disposition(signal).unwrap(),
+            ctrlc2::Disposition::Default
+        );
+
+        ctrlc2::ignore(&[signal]).unwrap();
+        assert_eq!(
+            ctrlc2::current_disposition(signal).unwrap(),
+            ctrlc2::Disposition::Ignored
+        );
+        ctrlc2::reset(&[signal]).unwrap();
+    }
+
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Console::CTRL_C_EVENT;
+
+        let handle = ctrlc2::set_handler(|| true).unwrap();
+        assert_eq!(
+            ctrlc2::current_disposition(CTRL_C_EVENT).unwrap(),
+            ctrlc2::Disposition::Own
+        );
+        ctrlc2::unset_handler().unwrap();
+        handle.join().unwrap();
+        assert_eq!(
+            ctrlc2::current_disposition(CTRL_C_EVENT).unwrap(),
+            ctrlc2::Disposition::Default
+        );
+    }
+}
+
+#[cfg(unix)]
+fn test_interrupted_wait() {
+    use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+    use std::os::unix::thread::JoinHandleExt;
+    use std::time::Duration;
+
+    extern "C" fn ignore(_: nix::libc::c_int) {}
+
+    ctrlc2::unset_handler().unwrap();
+
+    // Without SA_RESTART, the signal interrupts the read of the signal handling thread.
+    let action = SigAction::new(
+        SigHandler::Handler(ignore),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    let old = unsafe { sigaction(Signal::SIGUSR2, &action).unwrap() };
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler(move || {
+        tx.send(()).unwrap();
+        true
+    })
+    .unwrap();
+
+    std::thread::sleep(Duration::from_millis(100));
+    unsafe {
+        nix::libc::pthread_kill(handle.as_pthread_t(), nix::libc::SIGUSR2);
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(!handle.is_finished());
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    rx.recv().unwrap();
+    handle.join().unwrap();
+
+    unsafe { sigaction(Signal::SIGUSR2, &old).unwrap() };
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_result() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut count = 0;
+    let handle = ctrlc2::set_handler_result(
+        move || {
+            count += 1;
+            if count == 1 {
+                Err("first")
+            } else {
+                Ok(true)
+            }
+        },
+        move |e| {
+            tx.send(e).unwrap();
+            false
+        },
+    )
+    .unwrap();
+
+    // The error keeps the thread going, the second signal stops it.
+    for _ in 0..2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+    }
+    handle.join().unwrap();
+    assert_eq!(rx.try_recv().unwrap(), "first");
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_replace_handler() {
+    ctrlc2::unset_handler().unwrap();
+    assert!(matches!(
+        ctrlc2::replace_handler(|| true),
+        Err(ctrlc2::Error::NoHandler)
+    ));
+
+    let handle = ctrlc2::set_handler(|| panic!("handler should not run")).unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    ctrlc2::replace_handler(move || {
+        tx.send(()).unwrap();
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    rx.recv().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_shared_handler() {
+    use std::sync::atomic::AtomicUsize;
+
+    ctrlc2::unset_handler().unwrap();
+    assert!(ctrlc2::shared_handler().is_none());
+    assert!(matches!(
+        ctrlc2::swap_shared_handler(Arc::new(|| true)),
+        Err(ctrlc2::Error::NoHandler)
+    ));
+
+    let first_calls = Arc::new(AtomicUsize::new(0));
+    let calls = Arc::clone(&first_calls);
+    let first: ctrlc2::SharedHandler = Arc::new(move || {
+        calls.fetch_add(1, Ordering::SeqCst);
+        false
+    });
+    let handle = ctrlc2::set_shared_handler(Arc::clone(&first)).unwrap();
+    assert!(Arc::ptr_eq(&ctrlc2::shared_handler().unwrap(), &first));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    while first_calls.load(Ordering::SeqCst) == 0 {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let second: ctrlc2::SharedHandler = Arc::new(|| true);
+    let previous = ctrlc2::swap_shared_handler(Arc::clone(&second)).unwrap();
+    assert!(Arc::ptr_eq(&previous, &first));
+    assert!(Arc::ptr_eq(&ctrlc2::shared_handler().unwrap(), &second));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+    ctrlc2::unset_handler().unwrap();
+    assert!(ctrlc2::shared_handler().is_none());
+}
+
+fn test_set_handler_with_count() {
+    ctrlc2::unset_handler().unwrap();
+
+    // Signals received while paused are handled at once.
+    ctrlc2::pause();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_count(move |count| {
+        tx.send(count).unwrap();
+        true
+    })
+    .unwrap();
+    for _ in 0..3 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    ctrlc2::resume();
+
+    handle.join().unwrap();
+    assert_eq!(rx.recv().unwrap(), 3);
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_shutdown_flag() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (flag, handle) = ctrlc2::shutdown_flag().unwrap();
+    assert!(!flag.load(Ordering::Acquire));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    assert!(flag.load(Ordering::Acquire));
+    ctrlc2::unset_handler().unwrap();
+    #[cfg(unix)]
+    assert_eq!(ctrlc2::last_signal(), Some(ctrlc2::Signal::SIGINT));
+    #[cfg(windows)]
+    assert_eq!(
+        ctrlc2::last_signal(),
+        Some(windows_sys::Win32::System::Console::CTRL_C_EVENT)
+    );
+}
+
+fn test_set_handler_with_busy() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    let (busy_tx, busy_rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_busy(
+        move || {
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            true
+        },
+        move || busy_tx.send(()).unwrap(),
+    )
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    started_rx.recv().unwrap();
+
+    // The handler is still running, so the second signal only reports that.
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    busy_rx.recv().unwrap();
+
+    release_tx.send(()).unwrap();
+    handle.join().unwrap();
+    assert!(!ctrlc2::is_handler_set());
+    // The worker was joined along with the signal handling thread.
+    assert_eq!(
+        started_rx.try_recv(),
+        Err(std::sync::mpsc::TryRecvError::Disconnected)
+    );
+}
+
+fn test_set_handler_with_busy_join_timeout() {
+    use std::time::{Duration, Instant};
+
+    ctrlc2::unset_handler().unwrap();
+
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_busy(
+        move || {
+            started_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+            false
+        },
+        || {},
+    )
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    started_rx.recv().unwrap();
+
+    // The signal handling thread waits for the blocked worker, but not past the deadline.
+    ctrlc2::unset_handler().unwrap();
+    let releaser = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(2));
+        release_tx.send(()).unwrap();
+    });
+    let start = Instant::now();
+    let handle = ctrlc2::join_timeout(handle, Duration::from_millis(100))
+        .unwrap_err()
+        .into_handle();
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    ctrlc2::join_timeout(handle, Duration::from_secs(30)).unwrap();
+    releaser.join().unwrap();
+}
+
+fn test_coalesces_signals() {
+    const COALESCES: bool = ctrlc2::coalesces_signals();
+    assert_eq!(COALESCES, cfg!(unix));
+}
+
+fn test_pause() {
+    use std::time::Duration;
+
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut count = 0;
+    let handle = ctrlc2::set_handler(move || {
+        count += 1;
+        tx.send(()).unwrap();
+        count == 2
+    })
+    .unwrap();
+
+    ctrlc2::pause();
+    assert!(ctrlc2::is_paused());
+    for _ in 0..2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+    }
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+    // Both signals are delivered on resume.
+    ctrlc2::resume();
+    rx.recv().unwrap();
+    rx.recv().unwrap();
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+
+    // A paused handler can still be unset.
+    let handle = ctrlc2::set_handler(|| panic!("handler should not run")).unwrap();
+    ctrlc2::pause();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+    ctrlc2::resume();
+    assert!(!ctrlc2::is_paused());
+}
+
+fn test_channel() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (rx, handle) = ctrlc2::channel().unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    #[cfg(unix)]
+    assert_eq!(rx.recv().unwrap(), ctrlc2::Signal::SIGINT);
+    #[cfg(windows)]
+    assert_eq!(
+        rx.recv().unwrap(),
+        windows_sys::Win32::System::Console::CTRL_C_EVENT
+    );
+
+    // The thread exits on the first signal after the receiver is gone.
+    drop(rx);
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_sender() {
+    let (tx, rx) = std::sync::mpsc::sync_channel(1);
+    let handle = ctrlc2::set_handler_sender(tx).unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    #[cfg(unix)]
+    assert_eq!(rx.recv().unwrap(), ctrlc2::Signal::SIGINT);
+    #[cfg(windows)]
+    assert_eq!(
+        rx.recv().unwrap(),
+        windows_sys::Win32::System::Console::CTRL_C_EVENT
+    );
+
+    drop(rx);
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_for() {
+    match ctrlc2::set_handler_for(&[], |_| true) {
+        Err(ctrlc2::Error::NoSignals) => {}
+        ret => panic!("{:?}", ret),
+    }
+
+    #[cfg(unix)]
+    {
+        use ctrlc2::Signal;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = ctrlc2::set_handler_for(&[Signal::SIGTERM], move |signal| {
+            tx.send(signal).unwrap();
+            true
+        })
+        .unwrap();
+
+        nix::sys::signal::raise(Signal::SIGTERM).unwrap();
+
+        handle.join().unwrap();
+        assert_eq!(rx.recv().unwrap(), Signal::SIGTERM);
+        ctrlc2::unset_handler().unwrap();
+
+        // User-defined signals are reported as well.
+        let (tx, rx) = std::sync::mpsc::channel();
+        ctrlc2::set_handler_for(&[Signal::SIGUSR1, Signal::SIGUSR2], move |signal| {
+            tx.send(signal).unwrap();
+            false
+        })
+        .unwrap();
+
+        nix::sys::signal::raise(Signal::SIGUSR2).unwrap();
+        assert_eq!(rx.recv().unwrap(), Signal::SIGUSR2);
+        nix::sys::signal::raise(Signal::SIGUSR1).unwrap();
+        assert_eq!(rx.recv().unwrap(), Signal::SIGUSR1);
+        ctrlc2::unset_handler().unwrap();
+    }
+}
+
+#[cfg(unix)]
+fn test_set_handlers() {
+    use ctrlc2::{Signal, SignalHandler};
+    use std::collections::HashMap;
+
+    ctrlc2::unset_handler().unwrap();
+    assert!(matches!(
+        ctrlc2::set_handlers(HashMap::new()),
+        Err(ctrlc2::Error::NoSignals)
+    ));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut handlers: HashMap<Signal, SignalHandler> = HashMap::new();
+    let usr1 = tx.clone();
+    handlers.insert(
+        Signal::SIGUSR1,
+        Box::new(move || {
+            usr1.send("usr1").unwrap();
+            false
+        }),
+    );
+    handlers.insert(
+        Signal::SIGUSR2,
+        Box::new(move || {
+            tx.send("usr2").unwrap();
+            true
+        }),
+    );
+    let handle = ctrlc2::set_handlers(handlers).unwrap();
+
+    nix::sys::signal::raise(Signal::SIGUSR1).unwrap();
+    assert_eq!(rx.recv().unwrap(), "usr1");
+    nix::sys::signal::raise(Signal::SIGUSR2).unwrap();
+    assert_eq!(rx.recv().unwrap(), "usr2");
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_force_exit_threshold() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    ctrlc2::set_handler_with_force_exit_after(move || tx.send(()).unwrap(), 1, 3).unwrap();
+
+    // Only the third signal would exit the process.
+    for _ in 0..2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        rx.recv().unwrap();
+    }
+
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_force_exit_delay() {
+    use std::time::Duration;
+
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (handle, cleanup) = ctrlc2::set_handler_with_force_exit_delay(
+        move || tx.send(()).unwrap(),
+        1,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    rx.recv().unwrap();
+
+    // The second signal waits for the cleanup, which completes before the deadline.
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(!cleanup.is_complete());
+    cleanup.complete();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    std::thread::sleep(Duration::from_millis(100));
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+
+    // Without completing the cleanup, the process exits after the deadline.
+    #[cfg(unix)]
+    {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .env("CTRLC2_TEST_FORCE_EXIT_DELAY", "1")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"graceful\n");
+    }
+}
+
+#[cfg(unix)]
+fn test_set_handler_with_info() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_info(move |info| {
+        tx.send(info).unwrap();
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    let info = rx.recv().unwrap();
+    assert_eq!(info.signal, ctrlc2::Signal::SIGINT);
+    assert_eq!(info.pid, nix::unistd::getpid().as_raw());
+    assert_eq!(info.uid, unsafe { nix::libc::getuid() });
+    #[cfg(target_os = "linux")]
+    {
+        assert_eq!(info.origin, ctrlc2::SignalOrigin::Thread);
+        assert!(info.is_self_sent());
+    }
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(windows)]
+fn test_set_handler_with_event() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_event(move |event| {
+        tx.send(event).unwrap();
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    assert_eq!(rx.recv().unwrap(), ctrlc2::WindowsEvent::CtrlC);
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(windows)]
+fn test_ctrl_break_distinct() {
+    use ctrlc2::{Signal, SignalHandler, WindowsEvent};
+    use std::collections::HashMap;
+    use windows_sys::Win32::System::Console::{CTRL_BREAK_EVENT, CTRL_C_EVENT};
+
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_event(move |event| {
+        tx.send(event).unwrap();
+        event == WindowsEvent::CtrlC
+    })
+    .unwrap();
+    unsafe {
+        platform::raise_ctrl_break();
+    }
+    assert_eq!(rx.recv().unwrap(), WindowsEvent::CtrlBreak);
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(rx.recv().unwrap(), WindowsEvent::CtrlC);
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut handlers: HashMap<Signal, SignalHandler> = HashMap::new();
+    let ctrl_break = tx.clone();
+    handlers.insert(
+        CTRL_BREAK_EVENT,
+        Box::new(move || {
+            ctrl_break.send("break").unwrap();
+            false
+        }),
+    );
+    handlers.insert(
+        CTRL_C_EVENT,
+        Box::new(move || {
+            tx.send("c").unwrap();
+            true
+        }),
+    );
+    let handle = ctrlc2::set_handlers(handlers).unwrap();
+    unsafe {
+        platform::raise_ctrl_break();
+    }
+    assert_eq!(rx.recv().unwrap(), "break");
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(rx.recv().unwrap(), "c");
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_with_config() {
+    let config = ctrlc2::HandlerConfig {
+        name: Some("test-ctrl-c".into()),
+        stack_size: Some(256 * 1024),
+        ..Default::default()
+    };
+    let handle = ctrlc2::set_handler_with_config(config, || true).unwrap();
+    assert_eq!(handle.thread().name(), Some("test-ctrl-c"));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_raise_ctrl_c() {
+    ctrlc2::unset_handler().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler(move || {
+        tx.send(()).unwrap();
+        true
+    })
+    .unwrap();
+
+    ctrlc2::raise_ctrl_c().unwrap();
+    handle.join().unwrap();
+    rx.recv().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(feature = "testing")]
+fn test_simulate_signal() {
+    ctrlc2::unset_handler().unwrap();
+    #[cfg(unix)]
+    let signal = ctrlc2::Signal::SIGINT;
+    #[cfg(windows)]
+    let signal = windows_sys::Win32::System::Console::CTRL_C_EVENT;
+
+    assert!(matches!(
+        ctrlc2::simulate_signal(signal),
+        Err(ctrlc2::Error::NoHandler)
+    ));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_signal(move |signal| {
+        tx.send(signal).unwrap();
+        true
+    })
+    .unwrap();
+    ctrlc2::simulate_signal(signal).unwrap();
+    handle.join().unwrap();
+    assert_eq!(rx.recv().unwrap(), signal);
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_with_exit() {
+    ctrlc2::unset_handler().unwrap();
+
+    let handle = ctrlc2::set_handler_with_exit(|| true).unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(handle.join().unwrap(), ctrlc2::HandlerExit::Completed);
+    ctrlc2::unset_handler().unwrap();
+
+    let handle = ctrlc2::set_handler_with_exit(|| panic!("handler panicked")).unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(handle.join().unwrap(), ctrlc2::HandlerExit::Panicked);
+    ctrlc2::unset_handler().unwrap();
+
+    let handle = ctrlc2::set_handler_with_exit(|| true).unwrap();
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(handle.join().unwrap(), ctrlc2::HandlerExit::Unregistered);
+}
+
+fn test_set_handler_with_completion() {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct Unpark(std::thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on(mut completion: ctrlc2::Completion) -> ctrlc2::HandlerExit {
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match std::pin::Pin::new(&mut completion).poll(&mut cx) {
+                Poll::Ready(exit) => return exit,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    ctrlc2::unset_handler().unwrap();
+
+    let completion = ctrlc2::set_handler_with_completion(|| true).unwrap();
+    assert!(!completion.is_finished());
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(block_on(completion), ctrlc2::HandlerExit::Completed);
+    ctrlc2::unset_handler().unwrap();
+
+    let completion = ctrlc2::set_handler_with_completion(|| true).unwrap();
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(block_on(completion), ctrlc2::HandlerExit::Unregistered);
+}
+
+#[cfg(unix)]
+fn test_reinit_after_fork() {
+    use nix::libc;
+
+    ctrlc2::unset_handler().unwrap();
+    let (parent_tx, parent_rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler(move || {
+        parent_tx.send(()).unwrap();
+        true
+    })
+    .unwrap();
+
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        let code = std::panic::catch_unwind(|| {
+            assert!(matches!(
+                ctrlc2::set_handler(|| true),
+                Err(ctrlc2::Error::MultipleHandlers)
+            ));
+            ctrlc2::reinit_after_fork().unwrap();
+            assert!(!ctrlc2::is_handler_set());
+
+            let (tx, rx) = std::sync::mpsc::channel();
+            ctrlc2::set_handler(move || {
+                tx.send(()).unwrap();
+                true
+            })
+            .unwrap();
+            unsafe {
+                platform::raise_ctrl_c();
+            }
+            rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        })
+        .map_or(1, |()| 0);
+        unsafe { libc::_exit(code) };
+    }
+
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFEXITED(status));
+    assert_eq!(libc::WEXITSTATUS(status), 0);
+
+    // The handler of the parent is untouched and still handles signals.
+    assert!(ctrlc2::is_handler_set());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(!handle.is_finished());
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    parent_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .unwrap();
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_set_handler_with_error_sink() {
+    ctrlc2::unset_handler().unwrap();
+
+    let (sink, errors) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_error_sink(sink, || true).unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+
+    // No error occurred, and the sender was dropped with the thread.
+    assert!(errors.recv().is_err());
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_join_timeout() {
+    use std::time::{Duration, Instant};
+
+    ctrlc2::unset_handler().unwrap();
+
+    let handle = ctrlc2::set_handler_with_exit(|| true).unwrap();
+    let handle = ctrlc2::join_timeout(handle, Duration::from_millis(100))
+        .unwrap_err()
+        .into_handle();
+
+    let raiser = std::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+    });
+    let start = Instant::now();
+    let exit = ctrlc2::join_timeout(handle, Duration::from_secs(30)).unwrap();
+    assert_eq!(exit, ctrlc2::HandlerExit::Completed);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    raiser.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_builder() {
+    ctrlc2::unset_handler().unwrap();
+
+    assert!(matches!(
+        ctrlc2::Builder::new().signals(&[]).build(|_| true),
+        Err(ctrlc2::Error::NoSignals)
+    ));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::Builder::new()
+        .thread_name("test-builder")
+        .stack_size(256 * 1024)
+        .build(move |signal| {
+            tx.send(signal).unwrap();
+            true
+        })
+        .unwrap();
+    assert_eq!(handle.thread().name(), Some("test-builder"));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    #[cfg(unix)]
+    assert_eq!(rx.recv().unwrap(), ctrlc2::Signal::SIGINT);
+    #[cfg(windows)]
+    assert_eq!(
+        rx.recv().unwrap(),
+        windows_sys::Win32::System::Console::CTRL_C_EVENT
+    );
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_builder_panic_policy() {
+    ctrlc2::unset_handler().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::Builder::new()
+        .on_panic(move |payload| {
+            tx.send(*payload.downcast::<&str>().unwrap()).unwrap();
+            ctrlc2::PanicPolicy::Stop
+        })
+        .build(|_| panic!("stop here"))
+        .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    assert_eq!(rx.recv().unwrap(), "stop here");
+    assert!(ctrlc2::is_handler_set());
+    ctrlc2::unset_handler().unwrap();
+
+    // Returning nothing keeps handling signals.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut panicked = false;
+    let handle = ctrlc2::Builder::new()
+        .on_panic(|_| {})
+        .build(move |_| {
+            if !panicked {
+                panicked = true;
+                panic!("first signal");
+            }
+            tx.send(()).unwrap();
+            true
+        })
+        .unwrap();
+    for _ in 0..2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    handle.join().unwrap();
+    rx.recv().unwrap();
+    ctrlc2::unset_handler().unwrap();
+
+    #[cfg(unix)]
+    {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .env("CTRLC2_TEST_PANIC_EXIT", "1")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(7));
+    }
+}
+
+#[cfg(unix)]
+fn test_builder_ignore_sigpipe() {
+    use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+    ctrlc2::unset_handler().unwrap();
+    let default = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+    let previous = unsafe { sigaction(Signal::SIGPIPE, &default).unwrap() };
+
+    let handle = ctrlc2::Builder::new()
+        .ignore_sigpipe(true)
+        .build(|_| true)
+        .unwrap();
+    let current = unsafe { sigaction(Signal::SIGPIPE, &default).unwrap() };
+    assert_eq!(current.handler(), SigHandler::SigIgn);
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+
+    // Left alone if the handler can't be set, here because the self-pipe can't be created.
+    unsafe { sigaction(Signal::SIGPIPE, &default).unwrap() };
+    let mut limit = std::mem::MaybeUninit::uninit();
+    let limit = unsafe {
+        assert_eq!(
+            nix::libc::getrlimit(nix::libc::RLIMIT_NOFILE, limit.as_mut_ptr()),
+            0
+        );
+        limit.assume_init()
+    };
+    let lowered = nix::libc::rlimit {
+        rlim_cur: 0,
+        ..limit
+    };
+    unsafe { assert_eq!(nix::libc::setrlimit(nix::libc::RLIMIT_NOFILE, &lowered), 0) };
+    let result = ctrlc2::Builder::new().ignore_sigpipe(true).build(|_| true);
+    unsafe { assert_eq!(nix::libc::setrlimit(nix::libc::RLIMIT_NOFILE, &limit), 0) };
+    assert!(matches!(result, Err(ctrlc2::Error::System(_))));
+    let current = unsafe { sigaction(Signal::SIGPIPE, &default).unwrap() };
+    assert_eq!(current.handler(), SigHandler::SigDfl);
+
+    unsafe { sigaction(Signal::SIGPIPE, &previous).unwrap() };
+}
+
+fn test_builder_debounce() {
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    ctrlc2::unset_handler().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let handler_calls = Arc::clone(&calls);
+    let handle = ctrlc2::Builder::new()
+        .debounce(Duration::from_secs(60))
+        .build(move |_| {
+            handler_calls.fetch_add(1, Ordering::SeqCst);
+            false
+        })
+        .unwrap();
+
+    for expected in 1..=3 {
+        let received = ctrlc2::signals_received();
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        while ctrlc2::signals_received() == received {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // Give the handler the chance to run, which it must not do after the first signal.
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "signal {}", expected);
+    }
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+fn test_signals_blocked_elsewhere() {
+    use nix::sys::signal::{SigSet, Signal};
+    use std::sync::mpsc;
+
+    ctrlc2::unset_handler().unwrap();
+    let mut mask = SigSet::empty();
+    mask.add(Signal::SIGINT);
+    mask.thread_block().unwrap();
+
+    // Inherit the blocked mask, so the signal handling thread is the only one left to receive
+    // a process-directed signal.
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let stop_rx = Arc::new(std::sync::Mutex::new(stop_rx));
+    let workers = (0..4)
+        .map(|_| {
+            let stop_rx = Arc::clone(&stop_rx);
+            std::thread::spawn(move || {
+                let _ = stop_rx.lock().unwrap().recv();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let (tx, rx) = mpsc::channel();
+    let handle = ctrlc2::set_handler(move || {
+        tx.send(std::thread::current().name().map(String::from))
+            .unwrap();
+        false
+    })
+    .unwrap();
+
+    for _ in 0..3 {
+        unsafe {
+            nix::libc::kill(nix::libc::getpid(), nix::libc::SIGINT);
+        }
+        assert_eq!(rx.recv().unwrap().as_deref(), Some("ctrl-c"));
+    }
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+    drop(stop_tx);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    mask.thread_unblock().unwrap();
+}
+
+fn test_handler_thread_id() {
+    ctrlc2::unset_handler().unwrap();
+    assert!(ctrlc2::handler_thread_id().is_none());
+
+    let handle = ctrlc2::set_handler(|| true).unwrap();
+    let thread = ctrlc2::handler_thread_id().unwrap();
+    assert_eq!(thread.id(), handle.thread().id());
+    #[cfg(unix)]
+    {
+        use std::os::unix::thread::JoinHandleExt;
+        assert_eq!(thread.as_pthread_t(), handle.as_pthread_t());
+    }
+
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+    assert!(ctrlc2::handler_thread_id().is_none());
+
+    let source = ctrlc2::register_raw().unwrap();
+    assert!(ctrlc2::handler_thread_id().is_none());
+    drop(source);
+}
+
+#[cfg(unix)]
+fn test_skip_if_no_tty() {
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(ctrlc2::installed_signals(), None);
+
+    let handle = ctrlc2::set_handler(|| true).unwrap();
+    assert!(ctrlc2::installed_signals()
+        .unwrap()
+        .contains(&ctrlc2::Signal::SIGINT));
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+
+    let has_tty = std::fs::File::open("/dev/tty").is_ok();
+    let config = ctrlc2::HandlerConfig {
+        skip_if_no_tty: true,
+        ..Default::default()
+    };
+    let handle = ctrlc2::set_handler_with_config(config, || true).unwrap();
+    let signals = ctrlc2::installed_signals().unwrap();
+    assert_eq!(signals.contains(&ctrlc2::Signal::SIGINT), has_tty);
+    #[cfg(feature = "termination")]
+    assert!(signals.contains(&ctrlc2::Signal::SIGTERM));
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+}
+
+#[cfg(unix)]
+fn test_restart_syscalls() {
+    fn sigint_flags() -> nix::libc::c_int {
+        unsafe {
+            let mut action: nix::libc::sigaction = std::mem::zeroed();
+            nix::libc::sigaction(nix::libc::SIGINT, std::ptr::null(), &mut action);
+            action.sa_flags
+        }
+    }
+
+    for (restart_syscalls, restart) in [(None, true), (Some(false), false)] {
+        ctrlc2::unset_handler().unwrap();
+        let config = ctrlc2::HandlerConfig {
+            restart_syscalls,
+            ..Default::default()
+        };
+        let handle = ctrlc2::set_handler_with_config(config, || true).unwrap();
+        assert_eq!(sigint_flags() & nix::libc::SA_RESTART != 0, restart);
+        ctrlc2::unset_handler().unwrap();
+        handle.join().unwrap();
+    }
+}
+
+#[cfg(unix)]
+fn test_forward_to() {
+    use std::os::unix::process::ExitStatusExt;
+
+    ctrlc2::unset_handler().unwrap();
+
+    let mut child = std::process::Command::new("sleep")
+        .arg("10")
+        .spawn()
+        .unwrap();
+    let config = ctrlc2::HandlerConfig {
+        forward_to: Some(child.id() as i32),
+        ..Default::default()
+    };
+    let handle = ctrlc2::set_handler_with_config(config, || true).unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    assert_eq!(child.wait().unwrap().signal(), Some(nix::libc::SIGINT));
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_handler_panic() {
+    let (panic_tx, panic_rx) = std::sync::mpsc::channel::<()>();
+    let panic_tx = std::sync::Mutex::new(panic_tx);
+    let config = ctrlc2::HandlerConfig {
+        on_panic: Some(Arc::new(move |_| {
+            panic_tx.lock().unwrap().send(()).unwrap();
+            ctrlc2::PanicPolicy::Continue
+        })),
+        ..Default::default()
+    };
+
+    let mut panicked = false;
+    let handle = ctrlc2::set_handler_with_config(config, move || {
+        if !panicked {
+            panicked = true;
+            panic!("first signal");
+        }
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    panic_rx.recv().unwrap();
+
+    // The thread survived the panic and handles the second signal.
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_ignore() {
+    ctrlc2::unset_handler().unwrap();
+
+    #[cfg(unix)]
+    let signals = [ctrlc2::Signal::SIGINT];
+    #[cfg(windows)]
+    let signals = [windows_sys::Win32::System::Console::CTRL_C_EVENT];
+
+    // The process survives the ignored signal.
+    ctrlc2::ignore(&signals).unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    ctrlc2::reset(&signals).unwrap();
+    #[cfg(unix)]
+    unsafe {
+        let mut action: nix::libc::sigaction = std::mem::zeroed();
+        nix::libc::sigaction(nix::libc::SIGINT, std::ptr::null(), &mut action);
+        assert_eq!(action.sa_sigaction, nix::libc::SIG_DFL);
+    }
+}
+
+fn test_re_raise() {
+    let status = std::process::Command::new(std::env::current_exe().unwrap())
+        .env("CTRLC2_TEST_RE_RAISE", "1")
+        .status()
+        .unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(status.signal(), Some(nix::libc::SIGINT));
+    }
+    #[cfg(windows)]
+    assert_eq!(
+        status.code(),
+        Some(windows_sys::Win32::Foundation::STATUS_CONTROL_C_EXIT)
+    );
+}
+
+#[cfg(unix)]
+fn test_set_exit_handler() {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .env("CTRLC2_TEST_EXIT_HANDLER", "1")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(output.stdout, b"cleanup\n");
+
+    assert_eq!(ctrlc2::exit_code(ctrlc2::Signal::SIGINT), 130);
+    assert_eq!(ctrlc2::exit_code(ctrlc2::Signal::SIGHUP), 129);
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .env("CTRLC2_TEST_EXIT_HANDLER_WITH", "1")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(130));
+    assert_eq!(output.stdout, b"cleanup\n");
+}
+
+fn test_wait_for_signal() {
+    // Sequential calls install and tear down the handler each time.
+    for _ in 0..2 {
+        let raiser = std::thread::spawn(|| {
+            while !ctrlc2::is_handler_set() {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            unsafe {
+                platform::raise_ctrl_c();
+            }
+        });
+
+        let signal = ctrlc2::wait_for_signal().unwrap();
+        #[cfg(unix)]
+        assert_eq!(signal, ctrlc2::Signal::SIGINT);
+        #[cfg(windows)]
+        assert_eq!(signal, windows_sys::Win32::System::Console::CTRL_C_EVENT);
+        assert!(!ctrlc2::is_handler_set());
+        raiser.join().unwrap();
+    }
+}
+
+#[cfg(all(unix, feature = "quit"))]
+fn test_quit() {
+    use ctrlc2::Signal;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_with_signal(move |signal| {
+        tx.send(signal).unwrap();
+        true
+    })
+    .unwrap();
+
+    nix::sys::signal::raise(Signal::SIGQUIT).unwrap();
+
+    handle.join().unwrap();
+    assert_eq!(rx.recv().unwrap(), Signal::SIGQUIT);
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(feature = "tokio")]
+fn test_async_handler_timeout() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let received = runtime.block_on(async {
+        ctrlc2::set_async_handler_with_timeout(std::time::Duration::from_millis(50), async {
+            panic!("handler should not run")
+        })
+        .await
+        .await
+        .unwrap()
+    });
+    assert!(!received);
+}
+
+#[cfg(feature = "tokio")]
+fn test_async_handler_abort() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let handle = ctrlc2::set_async_handler(async { panic!("handler should not run") }).await;
+        tokio::task::yield_now().await;
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let handle = ctrlc2::set_async_handler(async move { tx.send(()).unwrap() }).await;
+        tokio::task::yield_now().await;
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        handle.await.unwrap();
+        rx.await.unwrap();
+    });
+}
+
+#[cfg(feature = "tokio")]
+fn test_async_handler_local() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let local = tokio::task::LocalSet::new();
+    let state = Rc::new(Cell::new(false));
+    let handled = Rc::clone(&state);
+    local.block_on(&runtime, async move {
+        let handle = ctrlc2::set_async_handler_local(async move { handled.set(true) }).await;
+        tokio::task::yield_now().await;
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        handle.await.unwrap();
+    });
+    assert!(state.get());
+}
+
+#[cfg(feature = "tokio")]
+fn test_arm_handler() {
+    ctrlc2::unset_handler().unwrap();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = runtime.block_on(async {
+        let handle = ctrlc2::arm_handler(move || {
+            tx.send(()).unwrap();
+            true
+        })
+        .await
+        .unwrap();
+        assert!(ctrlc2::is_handler_set());
+        assert!(matches!(
+            ctrlc2::arm_handler(|| true).await,
+            Err(ctrlc2::Error::MultipleHandlers)
+        ));
+        handle
+    });
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    rx.recv().unwrap();
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(feature = "tokio")]
+fn test_wait_for_signal_async() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let waiting = tokio::spawn(ctrlc2::wait_for_signal_async());
+        tokio::task::yield_now().await;
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        let signal = waiting.await.unwrap().unwrap();
+        assert_eq!(signal, ctrlc2::all_signals()[0]);
+    });
+}
+
+#[cfg(feature = "tokio")]
+fn test_signal_stream_tokio() {
+    use tokio_stream::StreamExt;
+
+    ctrlc2::unset_handler().unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let mut signals = ctrlc2::signal_stream_tokio().unwrap();
+    runtime.block_on(async {
+        for _ in 0..2 {
+            unsafe {
+                platform::raise_ctrl_c();
+            }
+            assert_eq!(signals.next().await, Some(ctrlc2::all_signals()[0]));
+        }
+        ctrlc2::unset_handler().unwrap();
+        assert_eq!(signals.next().await, None);
+    });
+}
+
+#[cfg(feature = "tokio")]
+fn test_set_notify() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let (notify, handle) = ctrlc2::set_notify().unwrap();
+    runtime.block_on(async {
+        let waiters = (0..2)
+            .map(|_| {
+                let notify = notify.clone();
+                tokio::spawn(async move { notify.notified().await })
+            })
+            .collect::<Vec<_>>();
+        tokio::task::yield_now().await;
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+    });
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+}
+
+#[cfg(feature = "tokio-util")]
+fn test_set_cancellation_token() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let (token, handle) = ctrlc2::set_cancellation_token().unwrap();
+    let child = token.child_token();
+    assert!(!token.is_cancelled());
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    assert!(token.is_cancelled());
+    runtime.block_on(async move { child.cancelled().await });
+    ctrlc2::unset_handler().unwrap();
+}
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+fn test_async_handler_cancel() {
+    use std::time::Duration;
+
+    ctrlc2::unset_handler().unwrap();
+    async_std::task::block_on(async {
+        let handle = ctrlc2::set_async_handler(async { panic!("handler should not run") }).await;
+        async_std::task::sleep(Duration::from_millis(50)).await;
+        assert!(ctrlc2::is_handler_set());
+        assert!(handle.cancel().await.is_none());
+        assert!(!ctrlc2::is_handler_set());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = ctrlc2::set_async_handler(async move { tx.send(()).unwrap() }).await;
+        async_std::task::sleep(Duration::from_millis(50)).await;
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        handle.await;
+        rx.recv().unwrap();
+
+        // Unset once completed, so another one can be set.
+        assert!(!ctrlc2::is_handler_set());
+        let handle = ctrlc2::set_async_handler(async {}).await;
+        async_std::task::sleep(Duration::from_millis(50)).await;
+        assert!(ctrlc2::is_handler_set());
+        assert!(handle.cancel().await.is_none());
+    });
+    ctrlc2::unset_handler().unwrap();
+}
+
 fn tests() {
     run_tests!(test_set_handler);
+    run_tests!(test_set_handler_with_signal);
+    run_tests!(test_all_signals);
+    run_tests!(test_parse_signal);
+    run_tests!(test_error_source);
+    run_tests!(test_set_handler_unsupported);
+    run_tests!(test_handler_reachability_check);
+    #[cfg(windows)]
+    run_tests!(test_windows_handler_reachable);
+    run_tests!(test_set_handler_flow);
+    run_tests!(test_set_handler_persistent);
+    run_tests!(test_set_handler_idempotent);
+    run_tests!(test_set_handler_with_state);
+    run_tests!(test_set_handler_result);
+    run_tests!(test_replace_handler);
+    run_tests!(test_set_shared_handler);
+    run_tests!(test_shutdown_flag);
+    run_tests!(test_set_handler_with_busy);
+    run_tests!(test_set_handler_with_busy_join_timeout);
+    run_tests!(test_coalesces_signals);
+    run_tests!(test_pause);
+    run_tests!(test_set_handler_with_count);
+    run_tests!(test_current_disposition);
+    #[cfg(unix)]
+    run_tests!(test_interrupted_wait);
+    run_tests!(test_channel);
+    run_tests!(test_set_handler_sender);
+    run_tests!(test_set_handler_for);
+    #[cfg(unix)]
+    run_tests!(test_set_handlers);
+    run_tests!(test_force_exit_threshold);
+    run_tests!(test_force_exit_delay);
+    #[cfg(unix)]
+    run_tests!(test_set_handler_with_info);
+    #[cfg(windows)]
+    run_tests!(test_set_handler_with_event);
+    #[cfg(windows)]
+    run_tests!(test_ctrl_break_distinct);
+    run_tests!(test_set_handler_with_config);
+    run_tests!(test_builder);
+    run_tests!(test_builder_debounce);
+    #[cfg(unix)]
+    run_tests!(test_builder_ignore_sigpipe);
+    run_tests!(test_builder_panic_policy);
+    run_tests!(test_handler_thread_id);
+    #[cfg(target_os = "linux")]
+    run_tests!(test_signals_blocked_elsewhere);
+    run_tests!(test_join_timeout);
+    run_tests!(test_set_handler_with_error_sink);
+    run_tests!(test_set_handler_with_exit);
+    run_tests!(test_set_handler_with_completion);
+    #[cfg(unix)]
+    run_tests!(test_reinit_after_fork);
+    run_tests!(test_raise_ctrl_c);
+    #[cfg(feature = "testing")]
+    run_tests!(test_simulate_signal);
+    run_tests!(test_handler_panic);
+    #[cfg(unix)]
+    run_tests!(test_forward_to);
+    #[cfg(unix)]
+    run_tests!(test_restart_syscalls);
+    #[cfg(unix)]
+    run_tests!(test_skip_if_no_tty);
+    run_tests!(test_wait_for_signal);
+    run_tests!(test_re_raise);
+    #[cfg(unix)]
+    run_tests!(test_set_exit_handler);
+    run_tests!(test_ignore);
+    #[cfg(all(unix, feature = "quit"))]
+    run_tests!(test_quit);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_async_handler_timeout);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_async_handler_abort);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_async_handler_local);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_arm_handler);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_wait_for_signal_async);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_signal_stream_tokio);
+    #[cfg(feature = "tokio")]
+    run_tests!(test_set_notify);
+    #[cfg(feature = "tokio-util")]
+    run_tests!(test_set_cancellation_token);
+    #[cfg(all(feature = "async-std", not(feature = "tokio")))]
+    run_tests!(test_async_handler_cancel);
 }
 
 fn main() {
+    // Run by test_re_raise in a child process.
+    if std::env::var_os("CTRLC2_TEST_RE_RAISE").is_some() {
+        #[cfg(unix)]
+        ctrlc2::re_raise(ctrlc2::Signal::SIGINT);
+        #[cfg(windows)]
+        ctrlc2::re_raise(windows_sys::Win32::System::Console::CTRL_C_EVENT);
+    }
+
+    // Run by test_set_exit_handler in a child process.
+    #[cfg(unix)]
+    if std::env::var_os("CTRLC2_TEST_EXIT_HANDLER").is_some() {
+        ctrlc2::set_exit_handler(42, || println!("cleanup")).unwrap();
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        loop {
+            std::thread::park();
+        }
+    }
+    #[cfg(unix)]
+    if std::env::var_os("CTRLC2_TEST_EXIT_HANDLER_WITH").is_some() {
+        ctrlc2::set_exit_handler_with(ctrlc2::exit_code, || println!("cleanup")).unwrap();
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        loop {
+            std::thread::park();
+        }
+    }
+
+    // Run by test_force_exit_delay in a child process.
+    #[cfg(unix)]
+    if std::env::var_os("CTRLC2_TEST_FORCE_EXIT_DELAY").is_some() {
+        let (_, _cleanup) = ctrlc2::set_handler_with_force_exit_delay(
+            || println!("graceful"),
+            3,
+            std::time::Duration::from_millis(100),
+        )
+        .unwrap();
+        for _ in 0..2 {
+            unsafe {
+                platform::raise_ctrl_c();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        loop {
+            std::thread::park();
+        }
+    }
+
+    // Run by test_builder_panic_policy in a child process.
+    if std::env::var_os("CTRLC2_TEST_PANIC_EXIT").is_some() {
+        let handle = ctrlc2::Builder::new()
+            .on_panic(|_| ctrlc2::PanicPolicy::Exit(7))
+            .build(|_| panic!("exit here"))
+            .unwrap();
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        handle.join().unwrap();
+        std::process::exit(1);
+    }
+
     run_harness(tests);
 }
@@ -0,0 +1,639 @@
+// Copyright (c) 2023 CtrlC developers
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>,
+// at your option. All files in the project carrying such
+// notice may not be copied, modified, or distributed except
+// according to those terms.
+
+#[macro_use]
+mod harness;
+use harness::{platform, run_harness};
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+fn test_unset_handler() {
+    assert!(!ctrlc2::is_handler_set());
+    assert_eq!(ctrlc2::signals_received(), 0);
+    let handle = ctrlc2::set_handler(|| panic!("handler should not run")).unwrap();
+    assert!(ctrlc2::is_handler_set());
+
+    ctrlc2::unset_handler().unwrap();
+    assert!(!ctrlc2::is_handler_set());
+    handle.join().unwrap();
+
+    // Unsetting twice is fine.
+    ctrlc2::unset_handler().unwrap();
+
+    let flag = Arc::new(AtomicBool::new(false));
+    let flag_handler = Arc::clone(&flag);
+    let handle = ctrlc2::try_set_handler(move || {
+        flag_handler.store(true, Ordering::SeqCst);
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    assert!(flag.load(Ordering::SeqCst));
+    assert_eq!(ctrlc2::signals_received(), 1);
+
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(ctrlc2::signals_received(), 0);
+}
+
+fn test_shutdown_handle() {
+    assert!(ctrlc2::shutdown_handle().is_none());
+
+    let handle = ctrlc2::set_handler(|| panic!("handler should not run")).unwrap();
+    let shutdown = ctrlc2::shutdown_handle().unwrap();
+    shutdown.shutdown().unwrap();
+    assert!(!ctrlc2::is_handler_set());
+    handle.join().unwrap();
+
+    // A stale handle leaves handlers set later alone.
+    let handle = ctrlc2::set_handler(|| true).unwrap();
+    shutdown.shutdown().unwrap();
+    assert!(ctrlc2::is_handler_set());
+
+    ctrlc2::shutdown_handle().unwrap().shutdown().unwrap();
+    handle.join().unwrap();
+}
+
+fn test_set_handler_once() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let flag_handler = Arc::clone(&flag);
+    let handle = ctrlc2::set_handler_once(move || {
+        assert!(!ctrlc2::is_handler_set());
+        flag_handler.store(true, Ordering::SeqCst);
+    })
+    .unwrap();
+    assert!(ctrlc2::is_handler_set());
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    handle.join().unwrap();
+    assert!(flag.load(Ordering::SeqCst));
+    assert!(!ctrlc2::is_handler_set());
+}
+
+fn test_set_handler_scoped() {
+    {
+        let _guard = ctrlc2::set_handler_scoped(|| panic!("handler should not run")).unwrap();
+        assert!(ctrlc2::is_handler_set());
+    }
+    assert!(!ctrlc2::is_handler_set());
+
+    let flag = Arc::new(AtomicBool::new(false));
+    let flag_handler = Arc::clone(&flag);
+    let guard = ctrlc2::set_handler_scoped(move || {
+        flag_handler.store(true, Ordering::SeqCst);
+        true
+    })
+    .unwrap();
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    guard.join().unwrap();
+    assert!(flag.load(Ordering::SeqCst));
+    assert!(!ctrlc2::is_handler_set());
+}
+
+fn test_handler_instances() {
+    for _ in 0..3 {
+        let handler = ctrlc2::Handler::new(|| true).unwrap();
+        assert!(ctrlc2::is_handler_set());
+        assert!(matches!(
+            ctrlc2::Handler::new(|| true),
+            Err(ctrlc2::Error::MultipleHandlers)
+        ));
+        drop(handler);
+        assert!(!ctrlc2::is_handler_set());
+    }
+}
+
+fn test_concurrent_set_handler() {
+    const THREADS: usize = 16;
+
+    for _ in 0..20 {
+        let barrier = Arc::new(std::sync::Barrier::new(THREADS));
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    ctrlc2::set_handler(|| true)
+                })
+            })
+            .collect();
+
+        let mut handles = Vec::new();
+        for thread in threads {
+            match thread.join().unwrap() {
+                Ok(handle) => handles.push(handle),
+                Err(ctrlc2::Error::MultipleHandlers) => {}
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        assert_eq!(handles.len(), 1);
+
+        ctrlc2::unset_handler().unwrap();
+        handles.pop().unwrap().join().unwrap();
+    }
+}
+
+fn test_add_handler() {
+    use std::sync::Mutex;
+
+    fn wait_for(calls: &Mutex<Vec<&str>>, len: usize) {
+        while calls.lock().unwrap().len() < len {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let first_calls = Arc::clone(&calls);
+    let first = ctrlc2::add_handler(move || {
+        first_calls.lock().unwrap().push("first");
+        false
+    })
+    .unwrap();
+    let second_calls = Arc::clone(&calls);
+    let second = ctrlc2::add_handler(move || {
+        second_calls.lock().unwrap().push("second");
+        false
+    })
+    .unwrap();
+    assert_ne!(first, second);
+    assert!(ctrlc2::is_handler_set());
+    assert!(matches!(
+        ctrlc2::set_handler(|| true),
+        Err(ctrlc2::Error::MultipleHandlers)
+    ));
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    wait_for(&calls, 2);
+    assert_eq!(*calls.lock().unwrap(), ["first", "second"]);
+
+    ctrlc2::remove_handler(first).unwrap();
+    assert!(matches!(
+        ctrlc2::remove_handler(first),
+        Err(ctrlc2::Error::NoHandler)
+    ));
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    wait_for(&calls, 3);
+    assert_eq!(*calls.lock().unwrap(), ["first", "second", "second"]);
+
+    ctrlc2::remove_handler(second).unwrap();
+    assert!(!ctrlc2::is_handler_set());
+
+    // A handler returning true removes itself, and the last one unsets the registration.
+    let third_calls = Arc::clone(&calls);
+    ctrlc2::add_handler(move || {
+        third_calls.lock().unwrap().push("third");
+        true
+    })
+    .unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    wait_for(&calls, 4);
+    while ctrlc2::is_handler_set() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+fn test_poisoned_lock() {
+    // Spawning a thread with an invalid name panics while the registration is locked.
+    let config = ctrlc2::HandlerConfig {
+        name: Some("ctrl\0c".into()),
+        ..Default::default()
+    };
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ctrlc2::set_handler_with_config(config, || true)
+    }));
+    assert!(result.is_err());
+
+    let handle = ctrlc2::set_handler(|| true).unwrap();
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+}
+
+#[cfg(unix)]
+fn test_spawn_failure() {
+    fn sigint_handler() -> nix::libc::sighandler_t {
+        unsafe {
+            let mut action: nix::libc::sigaction = std::mem::zeroed();
+            nix::libc::sigaction(nix::libc::SIGINT, std::ptr::null(), &mut action);
+            action.sa_sigaction
+        }
+    }
+    assert_eq!(sigint_handler(), nix::libc::SIG_DFL);
+
+    // No stack this large can be mapped, so spawning the signal handling thread fails.
+    let config = ctrlc2::HandlerConfig {
+        stack_size: Some(1 << 60),
+        ..Default::default()
+    };
+    assert!(matches!(
+        ctrlc2::set_handler_with_config(config, || true),
+        Err(ctrlc2::Error::System(_))
+    ));
+    assert!(!ctrlc2::is_handler_set());
+    assert_eq!(sigint_handler(), nix::libc::SIG_DFL);
+}
+
+fn test_unset_discards_pending() {
+    use std::time::Duration;
+
+    // Received by the signal handling thread, but not handled yet.
+    let handle = ctrlc2::set_handler(|| panic!("handler should not run")).unwrap();
+    ctrlc2::pause();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+    ctrlc2::resume();
+    assert_eq!(ctrlc2::signals_received(), 0);
+
+    // Queued while the handler is busy with an earlier one.
+    let runs = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler({
+        let runs = Arc::clone(&runs);
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            tx.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(300));
+            false
+        }
+    })
+    .unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    rx.recv().unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    ctrlc2::unset_handler().unwrap();
+    handle.join().unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    assert_eq!(ctrlc2::signals_received(), 0);
+
+    // Not even read from the os handler yet.
+    let source = ctrlc2::register_raw().unwrap();
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    drop(source);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler(move || {
+        tx.send(()).unwrap();
+        true
+    })
+    .unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(rx.try_recv().is_err());
+    assert_eq!(ctrlc2::signals_received(), 0);
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    handle.join().unwrap();
+    rx.recv().unwrap();
+    assert!(rx.try_recv().is_err());
+    ctrlc2::unset_handler().unwrap();
+}
+
+fn test_register_raw() {
+    let source = ctrlc2::register_raw().unwrap();
+    assert!(ctrlc2::is_handler_set());
+    assert!(source.try_recv().unwrap().is_none());
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        let mut fd = nix::libc::pollfd {
+            fd: source.as_raw_fd(),
+            events: nix::libc::POLLIN,
+            revents: 0,
+        };
+        assert_eq!(unsafe { nix::libc::poll(&mut fd, 1, 1000) }, 1);
+        assert_eq!(source.try_recv().unwrap(), Some(ctrlc2::Signal::SIGINT));
+    }
+    #[cfg(windows)]
+    assert_eq!(
+        source.recv().unwrap(),
+        windows_sys::Win32::System::Console::CTRL_C_EVENT
+    );
+    assert!(source.try_recv().unwrap().is_none());
+
+    assert!(source.take_pending().unwrap().is_empty());
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    #[cfg(unix)]
+    assert_eq!(source.take_pending().unwrap(), [ctrlc2::Signal::SIGINT]);
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::Threading::{WaitForSingleObject, INFINITE};
+
+        unsafe { WaitForSingleObject(source.as_raw_handle() as _, INFINITE) };
+        assert_eq!(
+            source.take_pending().unwrap(),
+            [windows_sys::Win32::System::Console::CTRL_C_EVENT]
+        );
+    }
+
+    // A signal read before the source runs into the unset handler is not lost.
+    #[cfg(unix)]
+    {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        ctrlc2::unset_handler().unwrap();
+        assert_eq!(source.take_pending().unwrap(), [ctrlc2::Signal::SIGINT]);
+        assert!(source.take_pending().unwrap().is_empty());
+    }
+
+    drop(source);
+    assert!(!ctrlc2::is_handler_set());
+}
+
+fn test_iter() {
+    let mut signals = ctrlc2::iter().unwrap();
+    assert!(ctrlc2::is_handler_set());
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    #[cfg(unix)]
+    assert_eq!(signals.next(), Some(ctrlc2::Signal::SIGINT));
+    #[cfg(windows)]
+    assert_eq!(
+        signals.next(),
+        Some(windows_sys::Win32::System::Console::CTRL_C_EVENT)
+    );
+
+    // Unsetting the handler ends the iteration.
+    let thread = std::thread::spawn(move || signals.next());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    ctrlc2::unset_handler().unwrap();
+    assert_eq!(thread.join().unwrap(), None);
+}
+
+fn test_set_handler_inline() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    ctrlc2::set_handler_inline(move |signal| {
+        tx.send(signal).unwrap();
+        true
+    })
+    .unwrap();
+    assert!(ctrlc2::is_handler_set());
+    assert_eq!(ctrlc2::poll_signal().unwrap(), None);
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    let signal = loop {
+        if let Some(signal) = ctrlc2::poll_signal().unwrap() {
+            break signal;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    assert_eq!(rx.try_recv().unwrap(), signal);
+    assert!(!ctrlc2::is_handler_set());
+    assert_eq!(ctrlc2::poll_signal().unwrap(), None);
+}
+
+fn test_register_inline() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    assert_eq!(ctrlc2::pump().unwrap(), 0);
+
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let handler_received = Rc::clone(&received);
+    ctrlc2::register_inline(move |signal| {
+        handler_received.borrow_mut().push(signal);
+        handler_received.borrow().len() == 2
+    })
+    .unwrap();
+    assert!(ctrlc2::is_handler_set());
+    assert_eq!(ctrlc2::pump().unwrap(), 0);
+
+    // Only the calling thread runs the handler.
+    std::thread::spawn(|| assert_eq!(ctrlc2::pump().unwrap(), 0))
+        .join()
+        .unwrap();
+
+    let mut handled = 0;
+    while handled < 2 {
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        handled += ctrlc2::pump().unwrap();
+    }
+    assert_eq!(received.borrow().len(), 2);
+    assert!(!ctrlc2::is_handler_set());
+    assert_eq!(ctrlc2::pump().unwrap(), 0);
+}
+
+fn test_wait_next() {
+    #[cfg(unix)]
+    let expected = ctrlc2::Signal::SIGINT;
+    #[cfg(windows)]
+    let expected = windows_sys::Win32::System::Console::CTRL_C_EVENT;
+
+    let raiser = std::thread::spawn(|| {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        unsafe {
+            platform::raise_ctrl_c();
+        }
+    });
+    assert_eq!(ctrlc2::wait_next().unwrap(), expected);
+    raiser.join().unwrap();
+    assert!(ctrlc2::is_handler_set());
+
+    // Signals received between two calls are queued.
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+    assert_eq!(ctrlc2::wait_next().unwrap(), expected);
+
+    let unsetter = std::thread::spawn(|| {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        ctrlc2::unset_handler().unwrap();
+    });
+    match ctrlc2::wait_next() {
+        Err(ctrlc2::Error::System(e)) => assert_eq!(e.kind(), std::io::ErrorKind::Interrupted),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    unsetter.join().unwrap();
+    assert!(!ctrlc2::is_handler_set());
+}
+
+#[cfg(all(target_os = "linux", feature = "signalfd"))]
+fn test_register_signalfd() {
+    let signals = ctrlc2::register_signalfd().unwrap();
+    assert!(signals.try_recv().unwrap().is_none());
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    assert_eq!(signals.recv().unwrap(), ctrlc2::Signal::SIGINT);
+    assert!(signals.try_recv().unwrap().is_none());
+}
+
+#[cfg(all(unix, feature = "sigwait"))]
+fn test_set_handler_sigwait() {
+    use nix::sys::signal::{SigSet, Signal};
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let handle = ctrlc2::set_handler_sigwait(move |signal| {
+        tx.send(signal).unwrap();
+        true
+    })
+    .unwrap();
+
+    // Process-directed, so the signal handling thread can receive it with sigwait.
+    unsafe {
+        nix::libc::kill(nix::libc::getpid(), nix::libc::SIGINT);
+    }
+
+    handle.join().unwrap();
+    assert_eq!(rx.recv().unwrap(), Signal::SIGINT);
+
+    let mut mask = SigSet::empty();
+    mask.add(Signal::SIGINT);
+    mask.thread_unblock().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+fn test_set_handler_realtime() {
+    use nix::libc;
+
+    let signal = ctrlc2::sigrtmin() + 1;
+    assert!(matches!(
+        ctrlc2::set_handler_realtime(&[libc::SIGINT], |_| true),
+        Err(ctrlc2::Error::System(_))
+    ));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut count = 0;
+    let handle = ctrlc2::set_handler_realtime(&[signal], move |received| {
+        tx.send(received).unwrap();
+        count += 1;
+        count == 3
+    })
+    .unwrap();
+
+    // Queued instead of coalesced, even if sent before the handler runs.
+    for _ in 0..3 {
+        unsafe {
+            libc::kill(libc::getpid(), signal);
+        }
+    }
+
+    handle.join().unwrap();
+    assert_eq!(rx.iter().collect::<Vec<_>>(), [signal; 3]);
+
+    unsafe {
+        let mut set = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, signal);
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut());
+    }
+}
+
+#[cfg(all(
+    feature = "kqueue",
+    any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "dragonfly"
+    )
+))]
+fn test_register_kqueue() {
+    let signals = ctrlc2::register_kqueue().unwrap();
+    assert!(signals.try_recv().unwrap().is_none());
+
+    unsafe {
+        platform::raise_ctrl_c();
+    }
+
+    assert_eq!(signals.recv().unwrap(), ctrlc2::Signal::SIGINT);
+    assert!(signals.try_recv().unwrap().is_none());
+}
+
+fn tests() {
+    run_tests!(test_unset_handler);
+    #[cfg(unix)]
+    run_tests!(test_spawn_failure);
+    run_tests!(test_register_raw);
+    run_tests!(test_unset_discards_pending);
+    run_tests!(test_iter);
+    run_tests!(test_set_handler_inline);
+    run_tests!(test_register_inline);
+    run_tests!(test_wait_next);
+    #[cfg(all(target_os = "linux", feature = "signalfd"))]
+    run_tests!(test_register_signalfd);
+    #[cfg(all(
+        feature = "kqueue",
+        any(
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd",
+            target_os = "dragonfly"
+        )
+    ))]
+    run_tests!(test_register_kqueue);
+    run_tests!(test_poisoned_lock);
+    run_tests!(test_concurrent_set_handler);
+    run_tests!(test_add_handler);
+    run_tests!(test_set_handler_scoped);
+    run_tests!(test_handler_instances);
+    run_tests!(test_set_handler_once);
+    run_tests!(test_shutdown_handle);
+    #[cfg(target_os = "linux")]
+    run_tests!(test_set_handler_realtime);
+    // Last, since it blocks the signals on the main thread.
+    #[cfg(all(unix, feature = "sigwait"))]
+    run_tests!(test_set_handler_sigwait);
+}
+
+fn main() {
+    run_harness(tests);
+}